@@ -0,0 +1,101 @@
+use super::Predicate;
+use axum::response::Response;
+
+/// [Predicate] which applies predicates in sequence, see [ChainedPredicate::new]
+///
+/// Each predicate receives the request returned by the previous one, so earlier predicates
+/// can insert extensions for later ones. The first rejection is returned and the rest are
+/// skipped.
+///
+/// # Example
+/// ```
+/// # use axum::{extract::Request, http::StatusCode, response::{IntoResponse, Response}, routing::{get, Router}};
+/// # use axum_help::filter::{ChainedPredicate, FilterExLayer};
+/// #
+/// #[derive(Clone)]
+/// struct UserId(u32);
+///
+/// let predicate = ChainedPredicate::new()
+///     .then(|mut request: Request| -> Result<Request, Response> {
+///         request.extensions_mut().insert(UserId(1));
+///         Ok(request)
+///     })
+///     .then(|request: Request| -> Result<Request, Response> {
+///         match request.extensions().get::<UserId>() {
+///             Some(UserId(1)) => Ok(request),
+///             _ => Err(StatusCode::FORBIDDEN.into_response()),
+///         }
+///     })
+///     .build();
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "hello" }))
+///     .layer(FilterExLayer::new(predicate));
+/// ```
+pub struct ChainedPredicate<R> {
+    predicates: Vec<Box<dyn ClonePredicate<R>>>,
+}
+
+impl<R> ChainedPredicate<R> {
+    /// start building a chain, predicates are applied in the order of [then](ChainedPredicateBuilder::then)
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> ChainedPredicateBuilder<R> {
+        ChainedPredicateBuilder {
+            predicates: Vec::new(),
+        }
+    }
+}
+
+impl<R> Clone for ChainedPredicate<R> {
+    fn clone(&self) -> Self {
+        Self {
+            predicates: self.predicates.iter().map(|p| p.clone_box()).collect(),
+        }
+    }
+}
+
+impl<R> Predicate<R> for ChainedPredicate<R> {
+    type Request = R;
+    type Response = Response;
+
+    fn check(&mut self, mut request: R) -> Result<Self::Request, Self::Response> {
+        for predicate in &mut self.predicates {
+            request = predicate.check(request)?;
+        }
+        Ok(request)
+    }
+}
+
+/// Builder of [ChainedPredicate]
+pub struct ChainedPredicateBuilder<R> {
+    predicates: Vec<Box<dyn ClonePredicate<R>>>,
+}
+
+impl<R> ChainedPredicateBuilder<R> {
+    /// append `predicate` to the chain
+    pub fn then<P>(mut self, predicate: P) -> Self
+    where
+        P: Predicate<R, Request = R, Response = Response> + Clone + Send + Sync + 'static,
+    {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    pub fn build(self) -> ChainedPredicate<R> {
+        ChainedPredicate {
+            predicates: self.predicates,
+        }
+    }
+}
+
+trait ClonePredicate<R>: Predicate<R, Request = R, Response = Response> + Send + Sync {
+    fn clone_box(&self) -> Box<dyn ClonePredicate<R>>;
+}
+
+impl<R, P> ClonePredicate<R> for P
+where
+    P: Predicate<R, Request = R, Response = Response> + Clone + Send + Sync + 'static,
+{
+    fn clone_box(&self) -> Box<dyn ClonePredicate<R>> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,98 @@
+use super::Predicate;
+use axum::{
+    extract::Request,
+    http::{header, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use mime::Mime;
+
+/// [Predicate] which rejects requests with unexpected `Content-Type` by
+/// `415 Unsupported Media Type`
+///
+/// Only requests with body (`POST`, `PUT` and `PATCH`) are checked, others are passed through.
+/// Parameters such as `charset` or `boundary` are ignored when comparing, and `*` can be used
+/// as subtype to allow all subtypes, such as `image/*`.
+///
+/// # Example
+/// ```
+/// # use axum::routing::{post, Router};
+/// # use axum_help::filter::{ContentTypeFilter, FilterExLayer};
+/// #
+/// let app: Router = Router::new()
+///     .route("/upload", post(|| async { "uploaded" }))
+///     .layer(FilterExLayer::new(ContentTypeFilter::new(vec![
+///         mime::IMAGE_STAR,
+///         mime::MULTIPART_FORM_DATA,
+///     ])));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ContentTypeFilter {
+    allowed: Vec<Mime>,
+}
+
+impl ContentTypeFilter {
+    /// Returns a new [ContentTypeFilter] which only allows `allowed` content types
+    pub fn new(allowed: Vec<Mime>) -> Self {
+        Self { allowed }
+    }
+
+    /// whether `content_type` matches one of the allowed content types
+    pub fn is_allowed(&self, content_type: &Mime) -> bool {
+        self.allowed.iter().any(|m| {
+            // `image/*` also allows `image/svg+xml`
+            m.type_() == content_type.type_()
+                && (m.subtype() == mime::STAR
+                    || (m.subtype() == content_type.subtype()
+                        && m.suffix() == content_type.suffix()))
+        })
+    }
+}
+
+impl Predicate<Request> for ContentTypeFilter {
+    type Request = Request;
+    type Response = Response;
+
+    fn check(&mut self, request: Request) -> Result<Self::Request, Self::Response> {
+        if !matches!(
+            *request.method(),
+            Method::POST | Method::PUT | Method::PATCH
+        ) {
+            return Ok(request);
+        }
+
+        let content_type = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<Mime>().ok());
+        match content_type {
+            Some(content_type) if self.is_allowed(&content_type) => Ok(request),
+            Some(content_type) => Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Unsupported content type: {}", content_type),
+            )
+                .into_response()),
+            None => Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Missing or invalid content type",
+            )
+                .into_response()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ContentTypeFilter;
+
+    #[test]
+    fn test_is_allowed() {
+        let filter = ContentTypeFilter::new(vec![mime::IMAGE_STAR, mime::APPLICATION_JSON]);
+        let allowed = |m: &str| filter.is_allowed(&m.parse().unwrap());
+        assert!(allowed("image/png"));
+        assert!(allowed("image/svg+xml"));
+        assert!(allowed("application/json; charset=utf-8"));
+        assert!(!allowed("application/ld+json"));
+        assert!(!allowed("text/plain"));
+    }
+}
@@ -1,4 +1,4 @@
-use super::predicate::AsyncPredicate;
+use super::predicate::{AsyncPredicate, ResponseTransformer};
 use axum::response::Response;
 use futures_core::ready;
 use pin_project_lite::pin_project;
@@ -103,3 +103,65 @@ where
         }
     }
 }
+
+pin_project! {
+    /// Transformed response future from [`AsyncTransformEx`](super::AsyncTransformEx) services.
+    ///
+    pub struct AsyncTransformFuture<P, F>
+    where
+        P: ResponseTransformer,
+    {
+        #[pin]
+        state: TransformState<F, P::Future>,
+        transformer: P
+    }
+}
+
+pin_project! {
+    #[project = TransformStateProj]
+    #[derive(Debug)]
+    enum TransformState<F, G> {
+        /// Waiting for the response future
+        WaitResponse { #[pin] response: F },
+        /// Waiting for the transform future
+        Transform { #[pin] transform: G }
+    }
+}
+
+impl<P, F> AsyncTransformFuture<P, F>
+where
+    P: ResponseTransformer,
+{
+    pub(super) fn new(response: F, transformer: P) -> Self {
+        Self {
+            state: TransformState::WaitResponse { response },
+            transformer,
+        }
+    }
+}
+
+impl<P, F, E> Future for AsyncTransformFuture<P, F>
+where
+    P: ResponseTransformer,
+    F: Future<Output = Result<Response, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            match this.state.as_mut().project() {
+                TransformStateProj::WaitResponse { response } => {
+                    let response = ready!(response.poll(cx))?;
+                    let transform = this.transformer.transform(response);
+                    this.state.set(TransformState::Transform { transform });
+                }
+
+                TransformStateProj::Transform { transform } => {
+                    return transform.poll(cx).map(Ok);
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use super::{AsyncFilterEx, FilterEx};
+use super::{AsyncFilterEx, AsyncTransformEx, FilterEx};
+use axum::http::Extensions;
 use tower::Layer;
 
 /// Conditionally dispatch requests to the inner service based on a synchronous [predicate](super::Predicate).
@@ -7,19 +8,32 @@ use tower::Layer;
 #[derive(Debug)]
 pub struct FilterExLayer<U: Clone> {
     predicate: U,
+    name: Option<String>,
 }
 
 impl<U: Clone> Clone for FilterExLayer<U> {
     fn clone(&self) -> Self {
         Self {
             predicate: self.predicate.clone(),
+            name: self.name.clone(),
         }
     }
 }
 
 impl<U: Clone> FilterExLayer<U> {
     pub fn new(predicate: U) -> Self {
-        Self { predicate }
+        Self {
+            predicate,
+            name: None,
+        }
+    }
+
+    /// Give the predicate a name, so it can be identified in debug logs
+    ///
+    /// Every check and rejection will be logged with `[predicate:{name}]` prefix.
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
     }
 }
 
@@ -27,7 +41,11 @@ impl<U: Clone, S> Layer<S> for FilterExLayer<U> {
     type Service = FilterEx<S, U>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        FilterEx::new(inner, self.predicate.clone())
+        FilterEx {
+            inner,
+            predicate: self.predicate.clone(),
+            name: self.name.clone(),
+        }
     }
 }
 
@@ -37,19 +55,55 @@ impl<U: Clone, S> Layer<S> for FilterExLayer<U> {
 #[derive(Debug)]
 pub struct AsyncFilterExLayer<U> {
     predicate: U,
+    state: Extensions,
 }
 
 impl<U: Clone> Clone for AsyncFilterExLayer<U> {
     fn clone(&self) -> Self {
         Self {
             predicate: self.predicate.clone(),
+            state: self.state.clone(),
         }
     }
 }
 
 impl<U> AsyncFilterExLayer<U> {
     pub fn new(predicate: U) -> Self {
-        Self { predicate }
+        Self {
+            predicate,
+            state: Extensions::new(),
+        }
+    }
+
+    /// Insert `state` into request extensions, so both the predicate and handlers can access it
+    ///
+    /// The predicate can also store what it computed (such as the validated user) in a shared
+    /// state, so handlers don't need to compute it again. Called multiple times with
+    /// different types, all of them are inserted.
+    ///
+    /// # Example
+    /// ```
+    /// # use axum::{extract::Request, response::Response, routing::get, Extension, Router};
+    /// # use axum_help::filter::AsyncFilterExLayer;
+    /// # use std::sync::Arc;
+    /// #
+    /// #[derive(Clone)]
+    /// struct Db(Arc<Vec<String>>);
+    ///
+    /// let db = Db(Arc::new(vec!["alice".to_string()]));
+    /// let app: Router = Router::new()
+    ///     .route("/", get(|Extension(db): Extension<Db>| async move { db.0.join(",") }))
+    ///     .layer(
+    ///         AsyncFilterExLayer::new(|request: Request| async move {
+    ///             let _db = request.extensions().get::<Db>();
+    ///             Ok::<_, Response>(request)
+    ///         })
+    ///         .with_state(db),
+    ///     );
+    /// ```
+    pub fn with_state<S: Clone + Send + Sync + 'static>(mut self, state: S) -> Self {
+        self.state.insert(state);
+        self
     }
 }
 
@@ -57,6 +111,40 @@ impl<U: Clone, S> Layer<S> for AsyncFilterExLayer<U> {
     type Service = AsyncFilterEx<S, U>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        AsyncFilterEx::new(inner, self.predicate.clone())
+        AsyncFilterEx {
+            inner,
+            predicate: self.predicate.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Transform responses returned by the inner service based on an asynchronous [transformer](super::ResponseTransformer)
+///
+/// This [`Layer`] produces instances of the [`AsyncTransformEx`] service.
+#[derive(Debug)]
+pub struct AsyncTransformExLayer<U> {
+    transformer: U,
+}
+
+impl<U: Clone> Clone for AsyncTransformExLayer<U> {
+    fn clone(&self) -> Self {
+        Self {
+            transformer: self.transformer.clone(),
+        }
+    }
+}
+
+impl<U> AsyncTransformExLayer<U> {
+    pub fn new(transformer: U) -> Self {
+        Self { transformer }
+    }
+}
+
+impl<U: Clone, S> Layer<S> for AsyncTransformExLayer<U> {
+    type Service = AsyncTransformEx<S, U>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AsyncTransformEx::new(inner, self.transformer.clone())
     }
 }
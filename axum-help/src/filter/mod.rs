@@ -27,17 +27,27 @@
 //! # }
 //!```
 //!
-use axum::{extract::Request, response::Response};
-use future::{AsyncResponseFuture, ResponseFuture};
+use axum::{extract::Request, http::Extensions, response::Response};
+pub use chain::{ChainedPredicate, ChainedPredicateBuilder};
+pub use content_type::ContentTypeFilter;
+use future::{AsyncResponseFuture, AsyncTransformFuture, ResponseFuture};
 use futures_util::StreamExt;
-pub use layer::{AsyncFilterExLayer, FilterExLayer};
-pub use predicate::{AsyncPredicate, Predicate};
+pub use layer::{AsyncFilterExLayer, AsyncTransformExLayer, FilterExLayer};
+pub use predicate::{
+    AsyncPredicate, BoxedAsyncPredicate, MappedPredicate, Predicate, ResponseTransformer,
+};
 use std::task::{Context, Poll};
+pub use timeout::Timeout;
 use tower::Service;
+pub use user_agent::UserAgentFilter;
 
+mod chain;
+mod content_type;
 mod future;
 mod layer;
 mod predicate;
+mod timeout;
+mod user_agent;
 
 /// Conditionally dispatch requests to the inner service based on a [predicate].
 ///
@@ -46,6 +56,7 @@ mod predicate;
 pub struct FilterEx<T, U> {
     inner: T,
     predicate: U,
+    name: Option<String>,
 }
 
 impl<T: Clone, U: Clone> Clone for FilterEx<T, U> {
@@ -53,6 +64,7 @@ impl<T: Clone, U: Clone> Clone for FilterEx<T, U> {
         Self {
             inner: self.inner.clone(),
             predicate: self.predicate.clone(),
+            name: self.name.clone(),
         }
     }
 }
@@ -60,7 +72,17 @@ impl<T: Clone, U: Clone> Clone for FilterEx<T, U> {
 impl<T, U: Clone> FilterEx<T, U> {
     /// Returns a new [FilterEx] service wrapping `inner`
     pub fn new(inner: T, predicate: U) -> Self {
-        Self { inner, predicate }
+        Self {
+            inner,
+            predicate,
+            name: None,
+        }
+    }
+
+    /// Give the predicate a name, so it can be identified in debug logs
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
     }
 
     /// Returns a new [Layer](tower::Layer) that wraps services with a [FilterEx] service
@@ -108,13 +130,23 @@ where
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
+        if let Some(name) = &self.name {
+            log::debug!("[predicate:{}] check called", name);
+        }
+
         match self.predicate.check(req) {
             Ok(req) => ResponseFuture::Future {
                 future: self.inner.call(req),
             },
-            Err(response) => ResponseFuture::Error {
-                response: Some(response),
-            },
+            Err(response) => {
+                if let Some(name) = &self.name {
+                    log::debug!("[predicate:{}] rejected: {}", name, response.status());
+                }
+
+                ResponseFuture::Error {
+                    response: Some(response),
+                }
+            }
         }
     }
 }
@@ -126,6 +158,7 @@ where
 pub struct AsyncFilterEx<T, U> {
     inner: T,
     predicate: U,
+    state: Extensions,
 }
 
 impl<T: Clone, U: Clone> Clone for AsyncFilterEx<T, U> {
@@ -133,6 +166,7 @@ impl<T: Clone, U: Clone> Clone for AsyncFilterEx<T, U> {
         Self {
             inner: self.inner.clone(),
             predicate: self.predicate.clone(),
+            state: self.state.clone(),
         }
     }
 }
@@ -140,7 +174,11 @@ impl<T: Clone, U: Clone> Clone for AsyncFilterEx<T, U> {
 impl<T, U> AsyncFilterEx<T, U> {
     /// Returns a new [AsyncFilterEx] service wrapping `inner`.
     pub fn new(inner: T, predicate: U) -> Self {
-        Self { inner, predicate }
+        Self {
+            inner,
+            predicate,
+            state: Extensions::new(),
+        }
     }
 
     /// Returns a new [Layer](tower::Layer) that wraps services with a [AsyncFilterEx] service
@@ -187,7 +225,7 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request) -> Self::Future {
+    fn call(&mut self, mut req: Request) -> Self::Future {
         use std::mem;
 
         let inner = self.inner.clone();
@@ -197,6 +235,7 @@ where
         // behind.
         let inner = mem::replace(&mut self.inner, inner);
 
+        req.extensions_mut().extend(self.state.clone());
         // Check the request
         let check = self.predicate.check(req);
 
@@ -204,7 +243,72 @@ where
     }
 }
 
+/// Transform responses returned by the inner service based on an
+/// asynchronous [transformer](ResponseTransformer)
+///
+#[derive(Debug)]
+pub struct AsyncTransformEx<T, U> {
+    inner: T,
+    transformer: U,
+}
+
+impl<T: Clone, U: Clone> Clone for AsyncTransformEx<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            transformer: self.transformer.clone(),
+        }
+    }
+}
+
+impl<T, U> AsyncTransformEx<T, U> {
+    /// Returns a new [AsyncTransformEx] service wrapping `inner`.
+    pub fn new(inner: T, transformer: U) -> Self {
+        Self { inner, transformer }
+    }
+
+    /// Returns a new [Layer](tower::Layer) that wraps services with a [AsyncTransformEx] service
+    /// with the given [ResponseTransformer]
+    ///
+    pub fn layer(transformer: U) -> AsyncTransformExLayer<U> {
+        AsyncTransformExLayer::new(transformer)
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, U> Service<Request> for AsyncTransformEx<T, U>
+where
+    T: Service<Request, Response = Response>,
+    U: ResponseTransformer + Clone,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = AsyncTransformFuture<U, T::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        AsyncTransformFuture::new(self.inner.call(req), self.transformer.clone())
+    }
+}
+
 pub async fn drain_body(request: Request) {
     let mut data_stream = request.into_body().into_data_stream();
-    while let Some(_) = data_stream.next().await {}
+    while data_stream.next().await.is_some() {}
 }
@@ -1,4 +1,6 @@
-use std::future::Future;
+use axum::response::Response;
+use futures_util::{future::MapOk, TryFutureExt};
+use std::{future::Future, pin::Pin};
 
 /// Checks a request synchronously
 ///
@@ -81,6 +83,31 @@ where
 ///     }
 /// }
 /// ```
+///
+/// # Tuples
+/// Tuples of up to 8 predicates check the request in sequence, each one receives the request
+/// returned by the previous one, and the first rejection is returned.
+///
+/// ```
+/// # use axum::{extract::Request, http::StatusCode, response::{IntoResponse, Response}, routing::{get, Router}};
+/// # use axum_help::filter::AsyncFilterExLayer;
+/// #
+/// let has_token = |request: Request| async move {
+///     match request.headers().contains_key("x-token") {
+///         true => Ok(request),
+///         false => Err(StatusCode::UNAUTHORIZED.into_response()),
+///     }
+/// };
+/// let is_admin = |request: Request| async move {
+///     match request.headers().get("x-role").is_some_and(|r| r == "admin") {
+///         true => Ok(request),
+///         false => Err::<_, Response>(StatusCode::FORBIDDEN.into_response()),
+///     }
+/// };
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "hello" }))
+///     .layer(AsyncFilterExLayer::new((has_token, is_admin)));
+/// ```
 pub trait AsyncPredicate<Request> {
     /// The type of requests returned by [`check`](AsyncPredicate::check)
     ///
@@ -98,6 +125,149 @@ pub trait AsyncPredicate<Request> {
     ///
     /// If the future resolves with [`Ok`], the request is forwarded to the inner service.
     fn check(&mut self, request: Request) -> Self::Future;
+
+    /// Wrap this predicate in a [BoxedAsyncPredicate], so predicates of different types
+    /// can be stored in one collection
+    fn into_boxed(self) -> BoxedAsyncPredicate<Request, Self::Request, Self::Response>
+    where
+        Self: Sized + Send + 'static,
+        Self::Future: Send + 'static,
+    {
+        Box::new(BoxFuturePredicate(self))
+    }
+
+    /// Transform the request forwarded to the inner service with `f` if the check succeeded
+    ///
+    /// # Example
+    /// ```
+    /// # use axum::{extract::Request, response::Response, routing::{get, Router}, Extension};
+    /// # use axum_help::filter::{AsyncFilterExLayer, AsyncPredicate};
+    /// #
+    /// #[derive(Clone)]
+    /// struct Principal {
+    ///     name: String,
+    /// }
+    ///
+    /// let predicate = (|request: Request| async move { Ok::<_, Response>(request) })
+    ///     .map_request(|mut request: Request| {
+    ///         let name = request
+    ///             .headers()
+    ///             .get("x-user")
+    ///             .and_then(|v| v.to_str().ok())
+    ///             .unwrap_or_default()
+    ///             .to_string();
+    ///         request.extensions_mut().insert(Principal { name });
+    ///         request
+    ///     });
+    /// let app: Router = Router::new()
+    ///     .route("/", get(|Extension(p): Extension<Principal>| async move { p.name }))
+    ///     .layer(AsyncFilterExLayer::new(predicate));
+    /// ```
+    fn map_request<F, N>(self, f: F) -> MappedPredicate<Self, F>
+    where
+        Self: Sized,
+        F: FnOnce(Self::Request) -> N + Clone,
+    {
+        MappedPredicate { inner: self, f }
+    }
+}
+
+/// [AsyncPredicate] which transforms the request of inner predicate, see
+/// [map_request](AsyncPredicate::map_request)
+#[derive(Clone, Debug)]
+pub struct MappedPredicate<P, F> {
+    inner: P,
+    f: F,
+}
+
+impl<R, P, F, N> AsyncPredicate<R> for MappedPredicate<P, F>
+where
+    P: AsyncPredicate<R>,
+    F: FnOnce(P::Request) -> N + Clone,
+{
+    type Request = N;
+    type Response = P::Response;
+    type Future = MapOk<P::Future, F>;
+
+    fn check(&mut self, request: R) -> Self::Future {
+        self.inner.check(request).map_ok(self.f.clone())
+    }
+}
+
+// chained (A, B, ..) predicates, up to 8
+macro_rules! tuple_predicate {
+    ($first:ident, $last:ident; $($prev:ident => $next:ident),+) => {
+        impl<R, $first, $($next),+> AsyncPredicate<R> for ($first, $($next),+)
+        where
+            $first: AsyncPredicate<R>,
+            $first::Future: Send + 'static,
+            $(
+                $next: AsyncPredicate<$prev::Request, Response = $first::Response> + Clone + Send + 'static,
+                $next::Future: Send,
+            )+
+        {
+            type Request = $last::Request;
+            type Response = $first::Response;
+            type Future = Pin<Box<dyn Future<Output = Result<Self::Request, Self::Response>> + Send>>;
+
+            #[allow(non_snake_case)]
+            fn check(&mut self, request: R) -> Self::Future {
+                let ($first, $($next),+) = self;
+                let first = $first.check(request);
+                $(let mut $next = $next.clone();)+
+                Box::pin(async move {
+                    let request = first.await?;
+                    $(let request = $next.check(request).await?;)+
+                    Ok(request)
+                })
+            }
+        }
+    };
+}
+
+tuple_predicate!(T1, T2; T1 => T2);
+tuple_predicate!(T1, T3; T1 => T2, T2 => T3);
+tuple_predicate!(T1, T4; T1 => T2, T2 => T3, T3 => T4);
+tuple_predicate!(T1, T5; T1 => T2, T2 => T3, T3 => T4, T4 => T5);
+tuple_predicate!(T1, T6; T1 => T2, T2 => T3, T3 => T4, T4 => T5, T5 => T6);
+tuple_predicate!(T1, T7; T1 => T2, T2 => T3, T3 => T4, T4 => T5, T5 => T6, T6 => T7);
+tuple_predicate!(T1, T8; T1 => T2, T2 => T3, T3 => T4, T4 => T5, T5 => T6, T6 => T7, T7 => T8);
+
+/// A type erased [AsyncPredicate], see [into_boxed](AsyncPredicate::into_boxed)
+pub type BoxedAsyncPredicate<R, Req, Res> = Box<
+    dyn AsyncPredicate<
+            R,
+            Request = Req,
+            Response = Res,
+            Future = Pin<Box<dyn Future<Output = Result<Req, Res>> + Send>>,
+        > + Send,
+>;
+
+impl<R, Req, Res> AsyncPredicate<R> for BoxedAsyncPredicate<R, Req, Res> {
+    type Request = Req;
+    type Response = Res;
+    type Future = Pin<Box<dyn Future<Output = Result<Req, Res>> + Send>>;
+
+    fn check(&mut self, request: R) -> Self::Future {
+        (**self).check(request)
+    }
+}
+
+/// Boxes the future returned by the inner predicate
+struct BoxFuturePredicate<P>(P);
+
+impl<R, P> AsyncPredicate<R> for BoxFuturePredicate<P>
+where
+    P: AsyncPredicate<R>,
+    P::Future: Send + 'static,
+{
+    type Request = P::Request;
+    type Response = P::Response;
+    type Future = Pin<Box<dyn Future<Output = Result<P::Request, P::Response>> + Send>>;
+
+    fn check(&mut self, request: R) -> Self::Future {
+        Box::pin(self.0.check(request))
+    }
 }
 
 impl<T, Req, Res, U, F> AsyncPredicate<T> for F
@@ -113,3 +283,48 @@ where
         self(request)
     }
 }
+
+/// Transforms a response asynchronously
+///
+/// # Example
+/// ```
+/// # use axum_help::filter::ResponseTransformer;
+/// # use axum::response::Response;
+/// # use std::pin::Pin;
+/// # use std::future::Future;
+/// #
+/// #[derive(Clone)]
+/// struct DebugHeader;
+///
+/// impl ResponseTransformer for DebugHeader {
+///     type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+///
+///     fn transform(&mut self, mut response: Response) -> Self::Future {
+///         Box::pin(async move {
+///             response
+///                 .headers_mut()
+///                 .insert("x-debug", "1".parse().unwrap());
+///             response
+///         })
+///     }
+/// }
+/// ```
+pub trait ResponseTransformer {
+    /// The future returned by [`transform`](ResponseTransformer::transform)
+    type Future: Future<Output = Response>;
+
+    /// Transform the response returned by inner service
+    fn transform(&mut self, response: Response) -> Self::Future;
+}
+
+impl<U, F> ResponseTransformer for F
+where
+    F: FnMut(Response) -> U,
+    U: Future<Output = Response>,
+{
+    type Future = U;
+
+    fn transform(&mut self, response: Response) -> Self::Future {
+        self(response)
+    }
+}
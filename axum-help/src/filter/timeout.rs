@@ -0,0 +1,82 @@
+use super::{AsyncPredicate, Predicate};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use futures_util::future::BoxFuture;
+use std::{sync::Arc, time::Duration};
+
+/// [AsyncPredicate] which runs a synchronous [Predicate] in blocking thread pool with a time limit
+///
+/// It's useful for predicates which may block, such as looking up credentials in files, so
+/// they won't block the tokio event loop. If the check takes longer than `duration`, the
+/// timeout response is returned, default is `503 Service Unavailable`. Note that the blocking
+/// check can not be cancelled, it keeps running in background after timeout.
+///
+/// # Example
+/// ```
+/// # use axum::{extract::Request, http::StatusCode, response::{IntoResponse, Response}, routing::{get, Router}};
+/// # use axum_help::filter::{AsyncFilterExLayer, Timeout};
+/// # use std::time::Duration;
+/// #
+/// let predicate = |request: Request| -> Result<Request, Response> {
+///     let users = std::fs::read_to_string("users.txt").unwrap_or_default();
+///     match request.headers().get("x-user").and_then(|u| u.to_str().ok()) {
+///         Some(user) if users.lines().any(|l| l == user) => Ok(request),
+///         _ => Err(StatusCode::FORBIDDEN.into_response()),
+///     }
+/// };
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "hello" }))
+///     .layer(AsyncFilterExLayer::new(Timeout::new(predicate, Duration::from_secs(1))));
+/// ```
+#[derive(Clone)]
+pub struct Timeout<P> {
+    inner: P,
+    duration: Duration,
+    on_timeout: Arc<dyn Fn() -> Response + Send + Sync>,
+}
+
+impl<P> Timeout<P> {
+    pub fn new(inner: P, duration: Duration) -> Self {
+        Self {
+            inner,
+            duration,
+            on_timeout: Arc::new(|| StatusCode::SERVICE_UNAVAILABLE.into_response()),
+        }
+    }
+
+    /// Set the response returned when the check timed out
+    pub fn on_timeout(mut self, f: impl Fn() -> Response + Send + Sync + 'static) -> Self {
+        self.on_timeout = Arc::new(f);
+        self
+    }
+}
+
+impl<P, R> AsyncPredicate<R> for Timeout<P>
+where
+    P: Predicate<R, Response = Response> + Clone + Send + 'static,
+    P::Request: Send + 'static,
+    R: Send + 'static,
+{
+    type Request = P::Request;
+    type Response = Response;
+    type Future = BoxFuture<'static, Result<Self::Request, Self::Response>>;
+
+    #[allow(clippy::result_large_err)]
+    fn check(&mut self, request: R) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let duration = self.duration;
+        let on_timeout = self.on_timeout.clone();
+        Box::pin(async move {
+            let check = tokio::task::spawn_blocking(move || inner.check(request));
+            match tokio::time::timeout(duration, check).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(e)) => {
+                    Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+                }
+                Err(_) => Err(on_timeout()),
+            }
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use super::Predicate;
+use axum::{
+    extract::Request,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use regex::Regex;
+
+/// [Predicate] which blocks or allows requests by `User-Agent` header
+///
+/// Rejected requests get `403 Forbidden`, and requests without `User-Agent` get
+/// `400 Bad Request` unless [require_user_agent](Self::require_user_agent) is set to `false`.
+///
+/// # Example
+/// ```
+/// # use axum::routing::{get, Router};
+/// # use axum_help::filter::{FilterExLayer, UserAgentFilter};
+/// # use regex::Regex;
+/// #
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "hello human" }))
+///     .layer(FilterExLayer::new(UserAgentFilter::block(vec![
+///         Regex::new("(?i).*bot.*").unwrap(),
+///     ])));
+/// ```
+#[derive(Clone, Debug)]
+pub struct UserAgentFilter {
+    patterns: Vec<Regex>,
+    allow: bool,
+    require_user_agent: bool,
+}
+
+impl UserAgentFilter {
+    /// Reject user agents matching any of `patterns`
+    pub fn block(patterns: Vec<Regex>) -> Self {
+        Self {
+            patterns,
+            allow: false,
+            require_user_agent: true,
+        }
+    }
+
+    /// Reject user agents matching none of `patterns`
+    pub fn allow_only(patterns: Vec<Regex>) -> Self {
+        Self {
+            patterns,
+            allow: true,
+            require_user_agent: true,
+        }
+    }
+
+    /// Whether reject requests without `User-Agent` header, default is `true`
+    pub fn require_user_agent(mut self, require: bool) -> Self {
+        self.require_user_agent = require;
+        self
+    }
+}
+
+impl Predicate<Request> for UserAgentFilter {
+    type Request = Request;
+    type Response = Response;
+
+    fn check(&mut self, request: Request) -> Result<Self::Request, Self::Response> {
+        let user_agent = match request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(user_agent) => user_agent,
+            None if self.require_user_agent => {
+                return Err((StatusCode::BAD_REQUEST, "Missing user agent").into_response())
+            }
+            None => return Ok(request),
+        };
+
+        let matched = self.patterns.iter().any(|p| p.is_match(user_agent));
+        if matched == self.allow {
+            Ok(request)
+        } else {
+            Err((StatusCode::FORBIDDEN, "User agent not allowed").into_response())
+        }
+    }
+}
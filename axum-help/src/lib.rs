@@ -1,6 +1,10 @@
 //! This crate make a series of enhancements for [Axum](axum)
 //!
-use axum::{http::StatusCode, response::IntoResponse, response::Response};
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    response::Response,
+};
 use std::fmt::{Debug, Display};
 
 pub mod filter;
@@ -30,15 +34,99 @@ pub mod filter;
 ///     Ok(())
 /// }
 /// ```
+///
+/// `message` may contain internal details, use [with_user_message](HttpError::with_user_message)
+/// to respond a safe one to end users instead.
 #[derive(PartialEq, Debug)]
 pub struct HttpError {
     pub message: String,
     pub status_code: StatusCode,
+    /// message responded instead of `message` if set
+    user_message: Option<String>,
+    /// log `message` with [log::error!] when converted into response
+    log_on_response: bool,
+}
+
+impl HttpError {
+    pub fn new(status_code: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            status_code,
+            user_message: None,
+            log_on_response: false,
+        }
+    }
+
+    /// respond `msg` instead of `message`, the internal `message` is kept for logging
+    ///
+    /// # Example
+    /// ```
+    /// # use axum_help::{HttpError, HttpResult};
+    /// fn handler() -> HttpResult<()> {
+    ///     std::fs::read("config.toml")
+    ///         .map_err(|e| {
+    ///             HttpError::from(e)
+    ///                 .with_user_message("server misconfigured")
+    ///                 .with_log_on_response(true)
+    ///         })?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_user_message(mut self, msg: impl Into<String>) -> Self {
+        self.user_message = Some(msg.into());
+        self
+    }
+
+    /// whether log `message` when converted into response
+    pub fn with_log_on_response(mut self, log: bool) -> Self {
+        self.log_on_response = log;
+        self
+    }
+
+    /// the message set by [with_user_message](Self::with_user_message)
+    pub fn user_message(&self) -> Option<&str> {
+        self.user_message.as_deref()
+    }
+
+    /// whether `message` is logged when converted into response, see
+    /// [with_log_on_response](Self::with_log_on_response)
+    pub fn log_on_response(&self) -> bool {
+        self.log_on_response
+    }
+
+    /// replace the status code with `f(status_code)`, such as turn 500 into 503 during maintenance
+    pub fn map_status(mut self, f: impl FnOnce(StatusCode) -> StatusCode) -> Self {
+        self.status_code = f(self.status_code);
+        self
+    }
+
+    /// convert into response with `extra_headers` set, such as `Retry-After`
+    ///
+    /// # Example
+    /// ```
+    /// # use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+    /// # use axum::response::Response;
+    /// # use axum_help::http_err;
+    /// fn handler() -> Response {
+    ///     let mut headers = HeaderMap::new();
+    ///     headers.insert(header::RETRY_AFTER, HeaderValue::from_static("120"));
+    ///     http_err!(StatusCode::SERVICE_UNAVAILABLE, "under maintenance")
+    ///         .into_response_with_headers(headers)
+    /// }
+    /// ```
+    pub fn into_response_with_headers(self, extra_headers: HeaderMap) -> Response {
+        let mut response = self.into_response();
+        response.headers_mut().extend(extra_headers);
+        response
+    }
 }
 
 impl IntoResponse for HttpError {
     fn into_response(self) -> Response {
-        let mut response = self.message.into_response();
+        if self.log_on_response {
+            log::error!("{:?}", self.message);
+        }
+        let mut response = self.user_message.unwrap_or(self.message).into_response();
         *response.status_mut() = self.status_code;
         response
     }
@@ -49,10 +137,7 @@ where
     E: Debug + Display + Sync + Send + 'static,
 {
     fn from(e: E) -> Self {
-        Self {
-            message: format!("{:?}", e),
-            status_code: StatusCode::INTERNAL_SERVER_ERROR,
-        }
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", e))
     }
 }
 
@@ -67,22 +152,16 @@ where
 #[macro_export]
 macro_rules! http_err {
     ($status: path, $fmt: literal, $($args: tt)+) => {
-        spa_rs::HttpError {
-            message: format!($fmt, $($args)+),
-            status_code: $status
-        }
+        $crate::HttpError::new($status, format!($fmt, $($args)+))
     };
     ($status: path, $msg: literal) => {
-        spa_rs::HttpError {
-            message: $msg.to_string(),
-            status_code: $status
-        }
+        $crate::HttpError::new($status, $msg.to_string())
     };
     ($fmt: literal, $($args: tt)+) => {
-        http_err!(spa_rs::http::StatusCode::INTERNAL_SERVER_ERROR, $fmt, $($args)+)
+        $crate::http_err!($crate::__private::StatusCode::INTERNAL_SERVER_ERROR, $fmt, $($args)+)
     };
     ($msg: literal) => {
-        http_err!(spa_rs::http::StatusCode::INTERNAL_SERVER_ERROR, $msg)
+        $crate::http_err!($crate::__private::StatusCode::INTERNAL_SERVER_ERROR, $msg)
     };
 }
 
@@ -96,6 +175,8 @@ macro_rules! http_err {
 /// If status code is not specified, [INTERNAL_SERVER_ERROR](StatusCode::INTERNAL_SERVER_ERROR)
 /// will be used.
 ///
+/// An existing [HttpError] can also be returned directly by `http_bail!(err)`.
+///
 /// # Example
 ///
 /// ```
@@ -106,14 +187,54 @@ macro_rules! http_err {
 /// fn get() -> HttpResult<()> {
 ///     http_bail!(StatusCode::BAD_REQUEST, "Bad Request: {}", "some reason");
 /// }
+///
+/// fn put() -> HttpResult<()> {
+///     let err = http_err!(StatusCode::CONFLICT, "already exists");
+///     http_bail!(err);
+/// }
 /// ```
 #[macro_export]
 macro_rules! http_bail {
+    ($msg: literal) => {
+        return Err($crate::http_err!($msg));
+    };
+    ($err: expr) => {
+        return Err($err);
+    };
     ($($args: tt)+) => {
-        return Err(spa_rs::http_err!($($args)+));
+        return Err($crate::http_err!($($args)+));
     };
 }
 
+/// Return early with an [`HttpError`] if `condition` is true
+///
+/// `http_bail_if!(condition, args...)` is equivalent to `if condition { `[`http_bail!(args...)`][http_bail!]` }`.
+///
+/// # Example
+///
+/// ```
+/// # use http::StatusCode;
+/// # use axum_help::{http_bail_if, HttpResult};
+/// #
+/// fn get(id: u32) -> HttpResult<()> {
+///     http_bail_if!(id == 0, StatusCode::BAD_REQUEST, "invalid id");
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! http_bail_if {
+    ($condition: expr, $($args: tt)+) => {
+        if $condition {
+            $crate::http_bail!($($args)+);
+        }
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use http::StatusCode;
+}
+
 /// Easily convert [std::result::Result] to [HttpResult]
 ///
 /// # Example
@@ -149,19 +270,18 @@ where
     where
         C: Display + Send + Sync + 'static,
     {
-        self.map_err(|e| HttpError {
-            message: format!("{}: {:?}", extra_msg, e),
-            status_code,
-        })
+        self.map_err(|e| HttpError::new(status_code, format!("{}: {:?}", extra_msg, e)))
     }
 
     fn http_error<C>(self, extra_msg: C) -> Result<T, HttpError>
     where
         C: Display + Send + Sync + 'static,
     {
-        self.map_err(|e| HttpError {
-            message: format!("{}: {:?}", extra_msg, e),
-            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+        self.map_err(|e| {
+            HttpError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("{}: {:?}", extra_msg, e),
+            )
         })
     }
 }
@@ -180,6 +300,8 @@ mod test {
         let error = HttpError {
             message: "aaa".to_string(),
             status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            user_message: None,
+            log_on_response: false,
         };
         assert_eq!(error, http_err!(StatusCode::INTERNAL_SERVER_ERROR, "aaa"));
         assert_eq!(
@@ -188,6 +310,18 @@ mod test {
         );
         assert_eq!(error, http_err!("aaa"));
         assert_eq!(error, http_err!("{}aa", "a"));
+
+        let error = error.with_user_message("bbb").with_log_on_response(true);
+        assert_eq!(error.message, "aaa");
+        assert_eq!(error.user_message(), Some("bbb"));
+        assert!(error.log_on_response());
+
+        let error = error.map_status(|s| match s {
+            StatusCode::INTERNAL_SERVER_ERROR => StatusCode::SERVICE_UNAVAILABLE,
+            s => s,
+        });
+        assert_eq!(error.status_code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.message, "aaa");
         Ok(())
     }
 }
@@ -5,6 +5,7 @@ use spa_rs::{filter::FilterExLayer, routing::get, routing::Router, spa_server_ro
 
 spa_server_root!("web/dist");
 
+#[allow(clippy::result_large_err)]
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
@@ -0,0 +1,222 @@
+//! Automatic certificate issuance and renewal with [ACME](https://datatracker.ietf.org/doc/html/rfc8555),
+//! such as [Let's Encrypt](https://letsencrypt.org/)
+//!
+//! Only HTTP-01 challenge is supported, so the domains must be reachable on port 80.
+use crate::ACME_CHALLENGE_PATH;
+use anyhow::{anyhow, bail, Context, Result};
+use axum::Router;
+use axum_server::{
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+    Handle,
+};
+use futures_util::future::try_join_all;
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, LetsEncrypt,
+    NewAccount, NewOrder, OrderStatus,
+};
+use log::{error, info};
+use rcgen::{CertificateParams, DistinguishedName, KeyPair};
+use std::{
+    fs,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::time::sleep;
+use tower_http::services::ServeDir;
+
+/// renew certificate when it is older than this, Let's Encrypt certificate is valid for 90 days
+const RENEW_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+const RENEW_CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+pub(crate) struct AutoCert {
+    pub(crate) domains: Vec<String>,
+    pub(crate) contact_email: String,
+    pub(crate) directory_url: String,
+    pub(crate) http_port: u16,
+}
+
+impl AutoCert {
+    pub(crate) fn new(domains: Vec<String>, contact_email: impl Into<String>) -> Self {
+        Self {
+            domains,
+            contact_email: contact_email.into(),
+            directory_url: LetsEncrypt::Production.url().to_string(),
+            http_port: 80,
+        }
+    }
+
+    /// Serve `router` with tls in all `servers`, the certificate is issued (or loaded from
+    /// `cache_dir`) before serving, and renewed in background
+    pub(crate) async fn serve(
+        self,
+        servers: Vec<axum_server::Server>,
+        router: Router,
+        handle: Handle,
+        challenge_dir: PathBuf,
+        cache_dir: PathBuf,
+    ) -> Result<()> {
+        fs::create_dir_all(&challenge_dir)?;
+        fs::create_dir_all(&cache_dir)?;
+
+        let challenge_router =
+            Router::new().nest_service(ACME_CHALLENGE_PATH, ServeDir::new(&challenge_dir));
+        let challenge_server =
+            axum_server::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, self.http_port)))
+                .handle(handle.clone())
+                .serve(challenge_router.into_make_service());
+        tokio::spawn(async move {
+            if let Err(e) = challenge_server.await {
+                error!("acme challenge server error: {:?}", e);
+            }
+        });
+
+        let (cert, key) = match self.cached(&cache_dir)? {
+            Some(cached) => cached,
+            None => self.issue(&challenge_dir, &cache_dir).await?,
+        };
+        let config = RustlsConfig::from_pem(cert, key).await?;
+
+        let renew_config = config.clone();
+        tokio::spawn(async move {
+            loop {
+                sleep(RENEW_CHECK_INTERVAL).await;
+                match self.cached(&cache_dir) {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => {}
+                    Err(e) => error!("check cached certificate error: {:?}", e),
+                }
+
+                info!("renewing certificate for {:?}", self.domains);
+                match self.issue(&challenge_dir, &cache_dir).await {
+                    Ok((cert, key)) => {
+                        if let Err(e) = renew_config.reload_from_pem(cert, key).await {
+                            error!("reload renewed certificate error: {:?}", e);
+                        }
+                    }
+                    Err(e) => error!("renew certificate error: {:?}", e),
+                }
+            }
+        });
+
+        let make_service = router.into_make_service_with_connect_info::<SocketAddr>();
+        try_join_all(servers.into_iter().map(|s| {
+            s.handle(handle.clone())
+                .acceptor(RustlsAcceptor::new(config.clone()))
+                .serve(make_service.clone())
+        }))
+        .await?;
+
+        Ok(())
+    }
+
+    /// cached certificate and key which does not need renew
+    fn cached(&self, cache_dir: &Path) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let cert_file = cache_dir.join("cert.pem");
+        let key_file = cache_dir.join("key.pem");
+        if !cert_file.exists() || !key_file.exists() {
+            return Ok(None);
+        }
+
+        let age = SystemTime::now()
+            .duration_since(fs::metadata(&cert_file)?.modified()?)
+            .unwrap_or_default();
+        if age > RENEW_AFTER {
+            return Ok(None);
+        }
+
+        Ok(Some((fs::read(cert_file)?, fs::read(key_file)?)))
+    }
+
+    async fn account(&self, cache_dir: &Path) -> Result<Account> {
+        let credentials_file = cache_dir.join("account.json");
+        if let Ok(credentials) = fs::read(&credentials_file) {
+            let credentials: AccountCredentials = serde_json::from_slice(&credentials)?;
+            return Ok(Account::from_credentials(credentials).await?);
+        }
+
+        let contact = format!("mailto:{}", self.contact_email);
+        let (account, credentials) = Account::create(
+            &NewAccount {
+                contact: &[&contact],
+                terms_of_service_agreed: true,
+                only_return_existing: false,
+            },
+            &self.directory_url,
+            None,
+        )
+        .await?;
+        fs::write(credentials_file, serde_json::to_vec(&credentials)?)?;
+        Ok(account)
+    }
+
+    async fn issue(&self, challenge_dir: &Path, cache_dir: &Path) -> Result<(Vec<u8>, Vec<u8>)> {
+        let account = self.account(cache_dir).await?;
+        let identifiers = self
+            .domains
+            .iter()
+            .map(|d| Identifier::Dns(d.clone()))
+            .collect::<Vec<_>>();
+        let mut order = account
+            .new_order(&NewOrder {
+                identifiers: &identifiers,
+            })
+            .await?;
+
+        let mut tokens = Vec::new();
+        for authz in order.authorizations().await? {
+            match authz.status {
+                AuthorizationStatus::Pending => {}
+                AuthorizationStatus::Valid => continue,
+                status => bail!("unexpected acme authorization status: {:?}", status),
+            }
+
+            let challenge = authz
+                .challenges
+                .iter()
+                .find(|c| c.r#type == ChallengeType::Http01)
+                .ok_or_else(|| anyhow!("no http-01 challenge found"))?;
+            let token_file = challenge_dir.join(&challenge.token);
+            fs::write(&token_file, order.key_authorization(challenge).as_str())?;
+            tokens.push(token_file);
+            order.set_challenge_ready(&challenge.url).await?;
+        }
+
+        let mut delay = Duration::from_millis(250);
+        for _ in 0..10 {
+            sleep(delay).await;
+            if let OrderStatus::Ready | OrderStatus::Invalid = order.refresh().await?.status {
+                break;
+            }
+
+            delay = (delay * 2).min(Duration::from_secs(10));
+        }
+
+        for token in tokens {
+            let _ = fs::remove_file(token);
+        }
+
+        let status = &order.state().status;
+        if *status != OrderStatus::Ready {
+            bail!("unexpected acme order status: {:?}", status);
+        }
+
+        let mut params = CertificateParams::new(self.domains.clone())?;
+        params.distinguished_name = DistinguishedName::new();
+        let private_key = KeyPair::generate()?;
+        let csr = params.serialize_request(&private_key)?;
+        order.finalize(csr.der()).await?;
+        let cert = loop {
+            match order.certificate().await? {
+                Some(cert) => break cert,
+                None => sleep(Duration::from_secs(1)).await,
+            }
+        };
+
+        let key = private_key.serialize_pem();
+        fs::write(cache_dir.join("cert.pem"), &cert).context("write certificate error")?;
+        fs::write(cache_dir.join("key.pem"), &key).context("write private key error")?;
+        info!("certificate issued for {:?}", self.domains);
+        Ok((cert.into_bytes(), key.into_bytes()))
+    }
+}
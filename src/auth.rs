@@ -1,16 +1,30 @@
+use crate::middleware::{buffer_body, under_prefix, RealIp};
 use anyhow::Result;
 use async_trait::async_trait;
 use axum::{
+    body::Body,
     extract::Request,
-    http::StatusCode,
+    http::{header, HeaderName, StatusCode, Uri},
     response::{IntoResponse, Response},
 };
-use axum_help::filter::{drain_body, AsyncPredicate};
-use headers::{authorization::Basic, Authorization, HeaderMapExt};
+use axum_help::filter::{drain_body, AsyncPredicate, BoxedAsyncPredicate, Predicate};
+use dashmap::DashMap;
+use futures_util::future::BoxFuture;
+use headers::{
+    authorization::{Basic, Bearer},
+    Authorization, HeaderMapExt,
+};
 use parking_lot::Mutex;
-use std::{collections::VecDeque, fmt::Display, future::Future, pin::Pin, sync::Arc};
+use std::{
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use subtle::ConstantTimeEq;
 
-use self::digest::unauthorized;
+use self::digest::{unauthorized, Nonces};
 
 #[async_trait]
 pub trait AuthCheckPredicate {
@@ -24,25 +38,96 @@ pub trait AuthCheckPredicate {
 
     fn username(&self) -> &str;
     fn password(&self) -> &str;
+
+    /// Limit failed attempts of each username, see [RateLimitedAuth]
+    fn rate_limit(self, max_attempts: u32, window: Duration) -> RateLimitedAuth<Self>
+    where
+        Self: Clone + Send + Sized,
+    {
+        RateLimitedAuth::new(self, max_attempts, window)
+    }
 }
 
+type SuccessHook<T> = Arc<dyn Fn(&T, &Request) + Send + Sync>;
+type FailureHook = Arc<dyn Fn(&str, &Request) + Send + Sync>;
+
 #[derive(Clone)]
-pub struct AsyncBasicAuth<T>(T, String)
+pub struct AsyncBasicAuth<T>
 where
-    T: AuthCheckPredicate + Clone + Send;
+    T: AuthCheckPredicate + Clone + Send,
+{
+    inner: T,
+    err: String,
+    on_success: Option<SuccessHook<T::CheckInfo>>,
+    on_failure: Option<FailureHook>,
+    whitelist: Vec<String>,
+    whitelist_prefixes: Vec<String>,
+}
 
 impl<T> AsyncBasicAuth<T>
 where
     T: AuthCheckPredicate + Clone + Send,
 {
     pub fn new(p: T) -> Self {
-        Self(p, "Need basic authenticate".to_string())
+        Self {
+            inner: p,
+            err: "Need basic authenticate".to_string(),
+            on_success: None,
+            on_failure: None,
+            whitelist: Vec::new(),
+            whitelist_prefixes: Vec::new(),
+        }
     }
 
     pub fn err_msg(mut self, msg: impl Into<String>) -> Self {
-        self.1 = msg.into();
+        self.err = msg.into();
+        self
+    }
+
+    /// Called when authenticate succeeded, with the check info and the request
+    ///
+    /// It's useful for audit log.
+    pub fn on_success(
+        mut self,
+        hook: impl Fn(&T::CheckInfo, &Request) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_success = Some(Arc::new(hook));
+        self
+    }
+
+    /// Called when authenticate failed, with the username (empty if no credential provided)
+    /// and the request
+    ///
+    /// It's useful for audit log, alerting, or blocking the client ip.
+    pub fn on_failure(mut self, hook: impl Fn(&str, &Request) + Send + Sync + 'static) -> Self {
+        self.on_failure = Some(Arc::new(hook));
+        self
+    }
+
+    /// Skip authentication for requests of exactly these paths, such as `/health` or `/login`
+    ///
+    /// Paths are matched against the request uri seen by this predicate, which is stripped
+    /// of the prefix when it is used inside a nested router.
+    pub fn whitelist_paths(mut self, paths: Vec<String>) -> Self {
+        self.whitelist.extend(paths);
+        self
+    }
+
+    /// Skip authentication for `prefix` and all paths under it, such as `/public`
+    pub fn whitelist_prefix(mut self, prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        self.whitelist_prefixes
+            .push(prefix.trim_end_matches('/').to_string());
         self
     }
+
+    fn whitelisted(&self, path: &str) -> bool {
+        self.whitelist.iter().any(|p| p == path)
+            || self
+                .whitelist_prefixes
+                .iter()
+                .any(|p| under_prefix(path, p))
+    }
 }
 
 impl<T> AsyncPredicate<Request> for AsyncBasicAuth<T>
@@ -54,22 +139,36 @@ where
     type Future = Pin<Box<dyn Future<Output = Result<Self::Request, Self::Response>> + Send>>;
 
     fn check(&mut self, mut request: Request) -> Self::Future {
-        let mut err = self.1.clone();
-        let auth = self.0.clone();
+        if self.whitelisted(request.uri().path()) {
+            return Box::pin(async move { Ok(request) });
+        }
+
+        let mut err = self.err.clone();
+        let auth = self.inner.clone();
+        let on_success = self.on_success.clone();
+        let on_failure = self.on_failure.clone();
         Box::pin(async move {
+            let mut username = String::new();
             if let Some(authorization) = request.headers().typed_get::<Authorization<Basic>>() {
+                username = authorization.username().to_string();
                 match auth
                     .check(authorization.username(), authorization.password())
                     .await
                 {
                     Err(e) => err = format!("check authorization error: {:?}", e),
                     Ok(ci) => {
+                        if let Some(hook) = on_success {
+                            hook(&ci, &request);
+                        }
                         request.extensions_mut().insert(ci);
                         return Ok(request);
                     }
                 }
             }
 
+            if let Some(hook) = on_failure {
+                hook(&username, &request);
+            }
             drain_body(request).await;
             Err((
                 StatusCode::UNAUTHORIZED,
@@ -89,7 +188,7 @@ where
     inner: T,
     err: String,
     srv_name: String,
-    nonces: Arc<Mutex<VecDeque<(String, String)>>>,
+    nonces: Nonces,
 }
 
 impl<T> AsyncDigestAuth<T>
@@ -101,10 +200,37 @@ where
             inner: p,
             srv_name: env!("CARGO_PKG_NAME").to_owned(),
             err: "Need digest authenticate".to_string(),
-            nonces: Arc::new(Mutex::new(VecDeque::new())),
+            nonces: Nonces::new(),
         }
     }
 
+    /// Secret key used to sign the nonce, default is a random key generated in [new](Self::new)
+    ///
+    /// Nonce is `base64(timestamp_ms || HMAC-SHA256(key, timestamp_ms))`, so tampered nonce
+    /// can be rejected immediately. Set the same key for all instances behind a load balancer.
+    pub fn server_secret(mut self, key: &[u8]) -> Self {
+        self.nonces.secret = key.into();
+        self
+    }
+
+    /// How long a nonce is valid since it was issued, default is 5 minutes
+    ///
+    /// Request with an expired nonce gets `stale=true` in `WWW-Authenticate` header,
+    /// so client can retry with the new nonce without prompting user again.
+    pub fn nonce_window(mut self, window: Duration) -> Self {
+        self.nonces.window = window;
+        self
+    }
+
+    /// Secret key used to derive opaque from nonce, default is a random opaque
+    ///
+    /// Opaque is `base64(HMAC-SHA256(key, nonce))`, and it's verified before checking the issued
+    /// nonces, so opaque issued by other server can not be replayed.
+    pub fn opaque_secret(mut self, key: &[u8]) -> Self {
+        self.nonces.opaque_secret = Some(key.into());
+        self
+    }
+
     pub fn srv_name(mut self, name: impl Into<String>) -> Self {
         self.srv_name = name.into();
         self
@@ -131,10 +257,9 @@ where
         let nonces = self.nonces.clone();
         Box::pin(async move {
             if let Some(auth_header) = request.headers().get("Authorization") {
-                let auth = digest::Authorization::from_header(
-                    auth_header.to_str().map_err(bad_request)?,
-                )
-                .map_err(bad_request)?;
+                let auth =
+                    digest::Authorization::from_header(auth_header.to_str().map_err(bad_request)?)
+                        .map_err(bad_request)?;
 
                 return auth.check(
                     inner.username(),
@@ -146,11 +271,669 @@ where
             }
 
             drain_body(request).await;
-            Err(unauthorized(nonces, err, srv_name))
+            Err(unauthorized(&nonces, err, srv_name, false))
+        })
+    }
+}
+
+/// A boxed auth method used by [CombinedAuth]
+///
+/// All auth predicates in this module can be boxed as `AuthMethod` directly,
+/// others can be converted by [into_boxed](AsyncPredicate::into_boxed).
+pub type AuthMethod = BoxedAsyncPredicate<Request, Request, Response>;
+
+/// Try multiple auth methods in order, request is forwarded once any of them succeeded
+///
+/// If all methods failed, `WWW-Authenticate` headers of all methods are returned in
+/// a single `401 Unauthorized` response, so client can pick the scheme it supports.
+///
+/// Request body is buffered, so every method can see the entire request. Bodies larger than
+/// [max_body_bytes](Self::max_body_bytes) are rejected with `413 Payload Too Large` before
+/// any method is tried.
+///
+/// # Example
+/// ```ignore
+/// let auth = CombinedAuth::new(vec![
+///     Box::new(AsyncBasicAuth::new(basic)),
+///     Box::new(AsyncDigestAuth::new(digest)),
+/// ]);
+/// ```
+#[derive(Clone)]
+pub struct CombinedAuth {
+    methods: Arc<Vec<Mutex<AuthMethod>>>,
+    max_body_bytes: usize,
+}
+
+/// Default max request body size buffered by [CombinedAuth]
+pub const DEFAULT_COMBINED_AUTH_MAX_BODY_BYTES: usize = 64 * 1024;
+
+impl CombinedAuth {
+    /// `methods` are tried in the given order
+    pub fn new(methods: Vec<AuthMethod>) -> Self {
+        Self {
+            methods: Arc::new(methods.into_iter().map(Mutex::new).collect()),
+            max_body_bytes: DEFAULT_COMBINED_AUTH_MAX_BODY_BYTES,
+        }
+    }
+
+    /// Max request body size buffered before authenticated, default is
+    /// [DEFAULT_COMBINED_AUTH_MAX_BODY_BYTES]
+    pub fn max_body_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_body_bytes = max_bytes;
+        self
+    }
+}
+
+impl AsyncPredicate<Request> for CombinedAuth {
+    type Request = Request;
+    type Response = Response;
+    type Future = BoxFuture<'static, Result<Self::Request, Self::Response>>;
+
+    fn check(&mut self, request: Request) -> Self::Future {
+        let methods = self.methods.clone();
+        let max_body_bytes = self.max_body_bytes;
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = buffer_body(body, max_body_bytes).await?;
+
+            let mut authenticates = Vec::new();
+            let mut last = None;
+            for method in methods.iter() {
+                let request = Request::from_parts(parts.clone(), Body::from(body.clone()));
+                let fut = method.lock().check(request);
+                match fut.await {
+                    Ok(request) => return Ok(request),
+                    Err(response) => {
+                        authenticates.extend(
+                            response
+                                .headers()
+                                .get_all(header::WWW_AUTHENTICATE)
+                                .iter()
+                                .cloned(),
+                        );
+                        last = Some(response);
+                    }
+                }
+            }
+
+            let mut response = last.unwrap_or_else(|| StatusCode::UNAUTHORIZED.into_response());
+            if !authenticates.is_empty() {
+                *response.status_mut() = StatusCode::UNAUTHORIZED;
+                let headers = response.headers_mut();
+                headers.remove(header::WWW_AUTHENTICATE);
+                for value in authenticates {
+                    headers.append(header::WWW_AUTHENTICATE, value);
+                }
+            }
+            Err(response)
+        })
+    }
+}
+
+/// Token bucket refilled at `max / window` tokens per second, holds at most `max` tokens
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BucketLimit {
+    max: u32,
+    window: Duration,
+}
+
+impl BucketLimit {
+    fn rate(&self) -> f64 {
+        self.max as f64 / self.window.as_secs_f64().max(f64::EPSILON)
+    }
+
+    fn refill(&self, bucket: &mut TokenBucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate()).min(self.max as f64);
+        bucket.updated = now;
+    }
+
+    /// time until the bucket of `key` has a token, `None` if it has one now
+    fn retry_after(&self, buckets: &Buckets, key: &str) -> Option<Duration> {
+        let mut bucket = buckets.map.get_mut(key)?;
+        self.refill(&mut bucket, Instant::now());
+        (bucket.tokens < 1.0).then(|| Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate()))
+    }
+
+    fn consume(&self, buckets: &Buckets, key: String) {
+        let now = Instant::now();
+        if buckets.map.len() >= MAX_RATE_LIMIT_BUCKETS && !buckets.map.contains_key(&key) {
+            buckets.sweep(self, now);
+            // still full of limited keys, bounding memory wins over tracking new keys
+            if buckets.map.len() >= MAX_RATE_LIMIT_BUCKETS {
+                return;
+            }
+        }
+
+        let mut bucket = buckets.map.entry(key).or_insert_with(|| TokenBucket {
+            tokens: self.max as f64,
+            updated: now,
+        });
+        self.refill(&mut bucket, now);
+        bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+    }
+}
+
+/// Token buckets of a [BucketLimit], holds at most [MAX_RATE_LIMIT_BUCKETS] buckets
+#[derive(Debug)]
+struct Buckets {
+    map: DashMap<String, TokenBucket>,
+    last_sweep: Mutex<Instant>,
+}
+
+impl Default for Buckets {
+    fn default() -> Self {
+        Self {
+            map: DashMap::new(),
+            last_sweep: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl Buckets {
+    /// forget buckets refilled to full, sweeping is O(n) so it's done at most once a second
+    fn sweep(&self, limit: &BucketLimit, now: Instant) {
+        let mut last_sweep = self.last_sweep.lock();
+        if now.saturating_duration_since(*last_sweep) < Duration::from_secs(1) {
+            return;
+        }
+        *last_sweep = now;
+        self.map.retain(|_, b| {
+            limit.refill(b, now);
+            b.tokens < limit.max as f64
+        });
+    }
+}
+
+const MAX_RATE_LIMIT_BUCKETS: usize = 10_000;
+
+/// Basic authentication limiting failed attempts of each username, against credential stuffing
+///
+/// Each username has a token bucket holding `max_attempts` tokens, refilled in `window`, every
+/// failed attempt takes a token. When the bucket is empty, requests of that username are
+/// rejected with `429 Too Many Requests` and `Retry-After` header, without checking credentials.
+/// Use [with_ip_limit](Self::with_ip_limit) to also limit by client ip. At most 10000 usernames
+/// or ips are tracked, new ones are not limited when all of them are still limited.
+///
+/// # Example
+/// ```
+/// # use spa_rs::routing::{get, Router};
+/// # use spa_rs::auth::{AuthCheckPredicate, RateLimitedAuth};
+/// # use axum_help::filter::AsyncFilterExLayer;
+/// # use std::time::Duration;
+/// #
+/// # fn app(auth: impl AuthCheckPredicate + Clone + Send + Sync + 'static) {
+/// let auth = RateLimitedAuth::new(auth, 5, Duration::from_secs(60))
+///     .with_ip_limit(20, Duration::from_secs(60));
+/// let app: Router = Router::new()
+///     .route("/admin", get(|| async { "admin" }))
+///     .layer(AsyncFilterExLayer::new(auth));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RateLimitedAuth<T>
+where
+    T: AuthCheckPredicate + Clone + Send,
+{
+    inner: AsyncBasicAuth<T>,
+    user_limit: BucketLimit,
+    user_buckets: Arc<Buckets>,
+    ip_limit: Option<BucketLimit>,
+    ip_buckets: Arc<Buckets>,
+    trust_real_ip: bool,
+}
+
+impl<T> RateLimitedAuth<T>
+where
+    T: AuthCheckPredicate + Clone + Send,
+{
+    pub fn new(inner: T, max_attempts: u32, window: Duration) -> Self {
+        Self::from_basic_auth(AsyncBasicAuth::new(inner), max_attempts, window)
+    }
+
+    /// Same as [new](Self::new), but wraps a configured [AsyncBasicAuth]
+    pub fn from_basic_auth(inner: AsyncBasicAuth<T>, max_attempts: u32, window: Duration) -> Self {
+        Self {
+            inner,
+            user_limit: BucketLimit {
+                max: max_attempts,
+                window,
+            },
+            user_buckets: Default::default(),
+            ip_limit: None,
+            ip_buckets: Default::default(),
+            trust_real_ip: false,
+        }
+    }
+
+    /// Also limit failed attempts of each client ip
+    ///
+    /// The peer address of the connection is used, which is the proxy when running behind
+    /// a reverse proxy, see [trust_real_ip](Self::trust_real_ip).
+    pub fn with_ip_limit(mut self, max_attempts: u32, window: Duration) -> Self {
+        self.ip_limit = Some(BucketLimit {
+            max: max_attempts,
+            window,
+        });
+        self
+    }
+
+    /// Limit by [RealIp](crate::middleware::RealIp) instead of the peer address
+    ///
+    /// Only enable it behind a reverse proxy which overwrites the real ip headers, otherwise
+    /// clients can rotate the header to bypass the ip limit.
+    pub fn trust_real_ip(mut self) -> Self {
+        self.trust_real_ip = true;
+        self
+    }
+}
+
+impl<T> AsyncPredicate<Request> for RateLimitedAuth<T>
+where
+    T: AuthCheckPredicate + Clone + Send + Sync + 'static,
+{
+    type Request = Request;
+    type Response = Response;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Request, Self::Response>> + Send>>;
+
+    fn check(&mut self, request: Request) -> Self::Future {
+        if self.inner.whitelisted(request.uri().path()) {
+            return Box::pin(async move { Ok(request) });
+        }
+
+        let username = request
+            .headers()
+            .typed_get::<Authorization<Basic>>()
+            .map(|a| a.username().to_string());
+        let ip = match self.trust_real_ip {
+            true => RealIp::from_extensions(request.extensions()),
+            false => RealIp::from_connect_info(request.extensions()),
+        }
+        .map(|ip| ip.0.to_string());
+
+        let user_limited = username
+            .as_ref()
+            .and_then(|u| self.user_limit.retry_after(&self.user_buckets, u));
+        let ip_limited = self
+            .ip_limit
+            .zip(ip.as_ref())
+            .and_then(|(limit, ip)| limit.retry_after(&self.ip_buckets, ip));
+        if let Some(retry_after) = user_limited.max(ip_limited) {
+            let retry_after = retry_after.as_secs_f64().ceil() as u64;
+            return Box::pin(async move {
+                drain_body(request).await;
+                Err((
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    "Too many failed authentication attempts",
+                )
+                    .into_response())
+            });
+        }
+
+        let check = self.inner.check(request);
+        let user_limit = self.user_limit;
+        let user_buckets = self.user_buckets.clone();
+        let ip_limit = self.ip_limit;
+        let ip_buckets = self.ip_buckets.clone();
+        Box::pin(async move {
+            let result = check.await;
+            // requests without credential are the challenge, not failed attempts
+            if let (Err(_), Some(username)) = (&result, username) {
+                user_limit.consume(&user_buckets, username);
+                if let Some((limit, ip)) = ip_limit.zip(ip) {
+                    limit.consume(&ip_buckets, ip);
+                }
+            }
+            result
         })
     }
 }
 
+/// Authenticate by a token in query parameter, such as download links or webhook callbacks
+///
+/// Respond `401 Unauthorized` if the parameter is absent, and `403 Forbidden` if `validator`
+/// returns false. The value is percent decoded before validating, and if the parameter is
+/// repeated, only the first one is validated. All occurrences are removed from the query string
+/// before forwarding, so they won't appear in logs of inner services.
+///
+/// # Example
+/// ```
+/// # use spa_rs::routing::{get, Router};
+/// # use spa_rs::auth::QueryParamAuth;
+/// # use axum_help::filter::FilterExLayer;
+/// #
+/// let app: Router = Router::new()
+///     .route("/download", get(|| async { "file content" }))
+///     .layer(FilterExLayer::new(QueryParamAuth::new("token", |t: &str| t == "secret")));
+/// ```
+#[derive(Clone)]
+pub struct QueryParamAuth<F> {
+    param: String,
+    validator: F,
+}
+
+impl<F> QueryParamAuth<F>
+where
+    F: Fn(&str) -> bool + Clone + Send + Sync,
+{
+    pub fn new(param_name: &str, validator: F) -> Self {
+        Self {
+            param: param_name.to_string(),
+            validator,
+        }
+    }
+}
+
+impl<F> Predicate<Request> for QueryParamAuth<F>
+where
+    F: Fn(&str) -> bool + Clone + Send + Sync,
+{
+    type Request = Request;
+    type Response = Response;
+
+    fn check(&mut self, mut request: Request) -> Result<Self::Request, Self::Response> {
+        let query = request.uri().query().unwrap_or_default();
+        let mut token = None;
+        let rest: Vec<&str> = query
+            .split('&')
+            .filter(|pair| {
+                let Some((name, value)) = form_urlencoded::parse(pair.as_bytes()).next() else {
+                    return false;
+                };
+                if name != self.param {
+                    return true;
+                }
+                if token.is_none() {
+                    token = Some(value.into_owned());
+                }
+                false
+            })
+            .collect();
+
+        let token = token.ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                format!("Missing query parameter {}", self.param),
+            )
+                .into_response()
+        })?;
+        if !(self.validator)(&token) {
+            return Err((StatusCode::FORBIDDEN, "Invalid token").into_response());
+        }
+
+        let path = request.uri().path();
+        let path_and_query = if rest.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, rest.join("&"))
+        };
+        let mut parts = request.uri().clone().into_parts();
+        parts.path_and_query = path_and_query.parse().ok();
+        *request.uri_mut() = Uri::from_parts(parts)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()).into_response())?;
+        Ok(request)
+    }
+}
+
+/// Store of api keys used by [ApiKeyAuth], maps a key to its principal
+pub trait AuthKeyStore: Send + Sync {
+    /// returns the principal which owns `key`, or `None` if the key is unknown or expired
+    fn principal(&self, key: &str) -> Option<String>;
+}
+
+/// A [AuthKeyStore] holding multiple keys at the same time, the keys can be added or
+/// removed while serving
+///
+/// This allows zero-downtime key rotation: add the new key, deploy clients with it,
+/// then remove the old key. Keys with `expires_at` in the past are rejected.
+#[derive(Debug, Default)]
+pub struct ApiKeyVault {
+    keys: DashMap<String, (String, Option<Instant>)>,
+}
+
+impl ApiKeyVault {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_key(&self, key: &str, principal: &str, expires_at: Option<Instant>) {
+        self.keys
+            .insert(key.to_string(), (principal.to_string(), expires_at));
+    }
+
+    pub fn remove_key(&self, key: &str) {
+        self.keys.remove(key);
+    }
+}
+
+impl AuthKeyStore for ApiKeyVault {
+    fn principal(&self, key: &str) -> Option<String> {
+        let entry = self.keys.get(key)?;
+        let (principal, expires_at) = entry.value();
+        match expires_at {
+            Some(expires_at) if Instant::now() > *expires_at => None,
+            _ => Some(principal.clone()),
+        }
+    }
+}
+
+/// The principal of api key authenticated by [ApiKeyAuth], can access by Extension
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiKeyPrincipal(pub String);
+
+/// Authenticate by api key in request header, the key is looked up in a shared [AuthKeyStore]
+///
+/// Respond `401 Unauthorized` if the header is absent, and `403 Forbidden` if the key is
+/// unknown or expired.
+///
+/// # Example
+/// ```
+/// # use std::sync::Arc;
+/// # use spa_rs::routing::{get, Router};
+/// # use spa_rs::auth::{ApiKeyAuth, ApiKeyVault};
+/// # use axum_help::filter::FilterExLayer;
+/// #
+/// let vault = Arc::new(ApiKeyVault::new());
+/// vault.add_key("old-key", "ci", None);
+/// vault.add_key("new-key", "ci", None);
+///
+/// let app: Router = Router::new()
+///     .route("/api/deploy", get(|| async { "ok" }))
+///     .layer(FilterExLayer::new(ApiKeyAuth::new("x-api-key", vault.clone())));
+///
+/// // after all clients switched to the new key
+/// vault.remove_key("old-key");
+/// ```
+pub struct ApiKeyAuth<S> {
+    header: HeaderName,
+    store: Arc<S>,
+}
+
+impl<S> Clone for ApiKeyAuth<S> {
+    fn clone(&self) -> Self {
+        Self {
+            header: self.header.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<S: AuthKeyStore> ApiKeyAuth<S> {
+    /// # Panics
+    ///
+    /// Panics if `header_name` is not a valid header name
+    pub fn new(header_name: &str, store: Arc<S>) -> Self {
+        Self {
+            header: HeaderName::try_from(header_name).expect("invalid header name"),
+            store,
+        }
+    }
+}
+
+impl<S: AuthKeyStore> Predicate<Request> for ApiKeyAuth<S> {
+    type Request = Request;
+    type Response = Response;
+
+    fn check(&mut self, mut request: Request) -> Result<Self::Request, Self::Response> {
+        let key = request
+            .headers()
+            .get(&self.header)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| {
+                (
+                    StatusCode::UNAUTHORIZED,
+                    format!("Missing header {}", self.header),
+                )
+                    .into_response()
+            })?;
+        let principal = self
+            .store
+            .principal(key)
+            .ok_or_else(|| (StatusCode::FORBIDDEN, "Invalid api key").into_response())?;
+
+        request.extensions_mut().insert(ApiKeyPrincipal(principal));
+        Ok(request)
+    }
+}
+
+/// Authenticate by a static token in `Authorization: Bearer <token>` header, such as
+/// protecting metrics or other internal endpoints
+///
+/// The token is compared in constant time. Respond `401 Unauthorized` if the header is
+/// absent or the token mismatches.
+///
+/// # Example
+/// ```
+/// # use spa_rs::routing::{get, Router};
+/// # use spa_rs::auth::BearerAuth;
+/// # use axum_help::filter::FilterExLayer;
+/// #
+/// let app: Router = Router::new()
+///     .route("/metrics", get(|| async { "requests_total 1" }))
+///     .layer(FilterExLayer::new(BearerAuth::new("secret")));
+/// ```
+#[derive(Clone)]
+pub struct BearerAuth {
+    token: Arc<[u8]>,
+}
+
+impl BearerAuth {
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: token.as_bytes().into(),
+        }
+    }
+}
+
+impl Predicate<Request> for BearerAuth {
+    type Request = Request;
+    type Response = Response;
+
+    fn check(&mut self, request: Request) -> Result<Self::Request, Self::Response> {
+        let matched = request
+            .headers()
+            .typed_get::<Authorization<Bearer>>()
+            .map(|auth| bool::from(auth.token().as_bytes().ct_eq(&self.token)))
+            .unwrap_or_default();
+        if !matched {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+            )
+                .into_response());
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(feature = "rustls")]
+pub use rustls::Certificate;
+
+/// Client certificates presented in tls handshake, the first one is the client's own
+/// certificate, can access by Extension when serving with rustls
+///
+/// It's empty if no certificate was presented, see [SpaServer::client_ca](crate::SpaServer::client_ca)
+#[cfg(feature = "rustls")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+#[derive(Clone, Debug)]
+pub struct PeerCertificates(pub Arc<[Certificate]>);
+
+/// Authenticate clients by tls client certificate, the identity returned by `verify_fn`
+/// can access by Extension
+///
+/// Responds `401 Unauthorized` if no certificate was presented, and `403 Forbidden` if
+/// `verify_fn` returns `None`. The certificate chain has been verified by the ca set in
+/// [SpaServer::client_ca](crate::SpaServer::client_ca) already, `verify_fn` only needs to
+/// map it to an identity.
+///
+/// # Example
+/// ```no_run
+/// # use spa_rs::{SpaServer, auth::MutualTlsAuth, filter::FilterExLayer, routing::Router};
+/// #[derive(Clone)]
+/// struct ClientIdentity(Vec<u8>);
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let srv = SpaServer::<()>::new()?.route_with_middleware(
+///     "/api",
+///     Router::new(),
+///     FilterExLayer::new(MutualTlsAuth::new(|cert| {
+///         Some(ClientIdentity(cert.0.clone()))
+///     })),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "rustls")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+#[derive(Clone, Debug)]
+pub struct MutualTlsAuth<F> {
+    verify_fn: F,
+}
+
+#[cfg(feature = "rustls")]
+impl<F, I> MutualTlsAuth<F>
+where
+    F: Fn(&Certificate) -> Option<I> + Clone + Send + Sync,
+    I: Clone + Send + Sync + 'static,
+{
+    pub fn new(verify_fn: F) -> Self {
+        Self { verify_fn }
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl<F, I> Predicate<Request> for MutualTlsAuth<F>
+where
+    F: Fn(&Certificate) -> Option<I> + Clone + Send + Sync,
+    I: Clone + Send + Sync + 'static,
+{
+    type Request = Request;
+    type Response = Response;
+
+    fn check(&mut self, mut request: Request) -> Result<Self::Request, Self::Response> {
+        let certificate = request
+            .extensions()
+            .get::<PeerCertificates>()
+            .and_then(|c| c.0.first())
+            .ok_or_else(|| {
+                (StatusCode::UNAUTHORIZED, "Client certificate required").into_response()
+            })?;
+        let identity = (self.verify_fn)(certificate).ok_or_else(|| {
+            (StatusCode::FORBIDDEN, "Client certificate rejected").into_response()
+        })?;
+
+        request.extensions_mut().insert(identity);
+        Ok(request)
+    }
+}
+
 fn bad_request(e: impl Display) -> Response {
     (
         StatusCode::BAD_REQUEST,
@@ -163,12 +946,119 @@ mod digest {
     use anyhow::{anyhow, bail, Result};
     use axum::{
         extract::Request,
-        http::StatusCode,
+        http::{Method, StatusCode},
         response::{IntoResponse, Response},
     };
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use hmac::{Hmac, Mac};
     use parking_lot::Mutex;
     use rand::{distributions::Alphanumeric, thread_rng, Rng};
-    use std::{collections::VecDeque, fmt::Debug, sync::Arc};
+    use sha2::Sha256;
+    use std::{
+        collections::VecDeque,
+        fmt::Debug,
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    type HmacSha256 = Hmac<Sha256>;
+
+    /// Issued nonces, and the key to sign them
+    #[derive(Clone)]
+    pub(super) struct Nonces {
+        pub(super) secret: Arc<[u8]>,
+        pub(super) window: Duration,
+        pub(super) opaque_secret: Option<Arc<[u8]>>,
+        list: Arc<Mutex<VecDeque<(String, String)>>>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub(super) enum NonceState {
+        Valid,
+        Stale,
+        Invalid,
+    }
+
+    impl Nonces {
+        pub(super) fn new() -> Self {
+            Self {
+                secret: thread_rng().gen::<[u8; 32]>().into(),
+                window: Duration::from_secs(5 * 60),
+                opaque_secret: None,
+                list: Arc::new(Mutex::new(VecDeque::new())),
+            }
+        }
+
+        fn mac(&self) -> HmacSha256 {
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC can take key of any size")
+        }
+
+        /// `base64(timestamp_ms || HMAC-SHA256(secret, timestamp_ms))`
+        pub(super) fn generate(&self) -> String {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let timestamp = timestamp.to_be_bytes();
+            let mut mac = self.mac();
+            mac.update(&timestamp);
+
+            let mut nonce = timestamp.to_vec();
+            nonce.extend_from_slice(&mac.finalize().into_bytes());
+            URL_SAFE_NO_PAD.encode(nonce)
+        }
+
+        fn opaque_mac(key: &[u8], nonce: &str) -> HmacSha256 {
+            let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+            mac.update(nonce.as_bytes());
+            mac
+        }
+
+        /// `base64(HMAC-SHA256(opaque_secret, nonce))` if opaque secret is set, or a random string
+        pub(super) fn opaque(&self, nonce: &str) -> String {
+            match &self.opaque_secret {
+                Some(key) => {
+                    URL_SAFE_NO_PAD.encode(Self::opaque_mac(key, nonce).finalize().into_bytes())
+                }
+                None => rand_string(32),
+            }
+        }
+
+        /// always true if opaque secret is not set
+        pub(super) fn validate_opaque(&self, nonce: &str, opaque: &str) -> bool {
+            let Some(key) = &self.opaque_secret else {
+                return true;
+            };
+
+            URL_SAFE_NO_PAD
+                .decode(opaque)
+                .is_ok_and(|opaque| Self::opaque_mac(key, nonce).verify_slice(&opaque).is_ok())
+        }
+
+        pub(super) fn validate(&self, nonce: &str) -> NonceState {
+            let Ok(nonce) = URL_SAFE_NO_PAD.decode(nonce) else {
+                return NonceState::Invalid;
+            };
+            if nonce.len() <= 8 {
+                return NonceState::Invalid;
+            }
+
+            let (timestamp, signature) = nonce.split_at(8);
+            let mut mac = self.mac();
+            mac.update(timestamp);
+            if mac.verify_slice(signature).is_err() {
+                return NonceState::Invalid;
+            }
+
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(timestamp);
+            let issued = UNIX_EPOCH + Duration::from_millis(u64::from_be_bytes(buf));
+            match SystemTime::now().duration_since(issued) {
+                Ok(elapsed) if elapsed > self.window => NonceState::Stale,
+                _ => NonceState::Valid,
+            }
+        }
+    }
 
     #[derive(Default, Debug)]
     pub(super) struct Authorization {
@@ -184,17 +1074,38 @@ mod digest {
     }
 
     impl Authorization {
+        #[allow(clippy::result_large_err)]
         pub(super) fn check(
             &self,
             username: impl AsRef<str>,
             password: impl AsRef<str>,
-            nonces: Arc<Mutex<VecDeque<(String, String)>>>,
+            nonces: Nonces,
             request: Request,
             srv_name: impl AsRef<str>,
         ) -> Result<Request, Response> {
+            let state = nonces.validate(&self.nonce);
+            if state == NonceState::Invalid {
+                return Err(unauthorized(&nonces, "invalid nonce", srv_name, false));
+            }
+
+            if !nonces.validate_opaque(&self.nonce, &self.opaque) {
+                return Err(unauthorized(&nonces, "invalid opaque", srv_name, false));
+            }
+
+            log::debug!("digest request: {:?}", request);
+            let matched = self.digest(username, password, request.method()) == self.response;
+            if state == NonceState::Stale {
+                // RFC 7616 only allows `stale=true` if the credentials are valid otherwise
+                return Err(if matched {
+                    unauthorized(&nonces, "nonce is stale", srv_name, true)
+                } else {
+                    unauthorized(&nonces, "invalid username or password", srv_name, false)
+                });
+            }
+
             let mut found_nonce = false;
             {
-                let mut nonce_list = nonces.lock();
+                let mut nonce_list = nonces.list.lock();
                 let mut index = nonce_list.len().saturating_sub(1);
 
                 for (nonce, opaque) in nonce_list.iter().rev() {
@@ -209,31 +1120,45 @@ mod digest {
             }
 
             if !found_nonce {
-                return Err(unauthorized(nonces, "invalid nonce or opaque", srv_name));
+                return Err(unauthorized(
+                    &nonces,
+                    "invalid nonce or opaque",
+                    srv_name,
+                    false,
+                ));
             }
 
-            log::debug!("digest request: {:?}", request);
+            if !matched {
+                return Err(unauthorized(
+                    &nonces,
+                    "invalid username or password",
+                    srv_name,
+                    false,
+                ));
+            }
+
+            Ok(request)
+        }
+
+        /// the expected `response` of `username` and `password`
+        pub(super) fn digest(
+            &self,
+            username: impl AsRef<str>,
+            password: impl AsRef<str>,
+            method: &Method,
+        ) -> String {
             let ha1 = md5::compute(format!(
                 "{}:{}:{}",
                 username.as_ref(),
                 self.realm,
                 password.as_ref()
             ));
-            let ha2 = md5::compute(format!("{}:{}", request.method(), self.uri));
-            let password = md5::compute(format!(
+            let ha2 = md5::compute(format!("{}:{}", method, self.uri));
+            let response = md5::compute(format!(
                 "{:x}:{}:{}:{}:{}:{:x}",
                 ha1, self.nonce, self.nc, self.cnonce, self.qop, ha2
             ));
-
-            if format!("{:x}", password) != self.response {
-                return Err(unauthorized(
-                    nonces,
-                    "invalid username or password",
-                    srv_name,
-                ));
-            }
-
-            Ok(request)
+            format!("{:x}", response)
         }
 
         const DIGEST_MARK: &'static str = "Digest";
@@ -275,21 +1200,25 @@ mod digest {
     }
 
     pub(super) fn unauthorized(
-        nonces: Arc<Mutex<VecDeque<(String, String)>>>,
+        nonces: &Nonces,
         msg: impl Into<String>,
         srv_name: impl AsRef<str>,
+        stale: bool,
     ) -> Response {
         let realm = format!("Login to {}", srv_name.as_ref());
-        let nonce = rand_string(32);
-        let opaque = rand_string(32);
+        let nonce = nonces.generate();
+        let opaque = nonces.opaque(&nonce);
 
-        let www_authenticate = format!(
+        let mut www_authenticate = format!(
             r#"Digest realm="{}",qop="auth",nonce="{}",opaque="{}""#,
             realm, nonce, opaque
         );
+        if stale {
+            www_authenticate.push_str(",stale=true");
+        }
 
         {
-            let mut nonce_list = nonces.lock();
+            let mut nonce_list = nonces.list.lock();
             while nonce_list.len() >= 256 {
                 nonce_list.pop_front();
             }
@@ -313,3 +1242,196 @@ mod digest {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::digest::{unauthorized, Authorization, NonceState, Nonces};
+    use super::{
+        ApiKeyVault, AuthKeyStore, BearerAuth, BucketLimit, Buckets, QueryParamAuth,
+        MAX_RATE_LIMIT_BUCKETS,
+    };
+    use crate::filter::Predicate;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{header, Method, StatusCode},
+    };
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_bucket_limit() {
+        let limit = BucketLimit {
+            max: 2,
+            window: Duration::from_secs(60),
+        };
+        let buckets = Buckets::default();
+        assert_eq!(limit.retry_after(&buckets, "alice"), None);
+        limit.consume(&buckets, "alice".to_string());
+        assert_eq!(limit.retry_after(&buckets, "alice"), None);
+        limit.consume(&buckets, "alice".to_string());
+        let retry_after = limit.retry_after(&buckets, "alice").unwrap();
+        assert!(retry_after > Duration::from_secs(29) && retry_after <= Duration::from_secs(30));
+        assert_eq!(limit.retry_after(&buckets, "bob"), None);
+
+        for i in 0..MAX_RATE_LIMIT_BUCKETS * 2 {
+            limit.consume(&buckets, i.to_string());
+        }
+        assert_eq!(buckets.map.len(), MAX_RATE_LIMIT_BUCKETS);
+    }
+
+    #[test]
+    fn test_api_key_vault() {
+        let vault = ApiKeyVault::new();
+        vault.add_key("old", "alice", None);
+        vault.add_key(
+            "new",
+            "alice",
+            Some(Instant::now() + Duration::from_secs(60)),
+        );
+        vault.add_key(
+            "expired",
+            "bob",
+            Some(Instant::now() - Duration::from_secs(1)),
+        );
+        assert_eq!(vault.principal("old").as_deref(), Some("alice"));
+        assert_eq!(vault.principal("new").as_deref(), Some("alice"));
+        assert_eq!(vault.principal("expired"), None);
+        assert_eq!(vault.principal("unknown"), None);
+
+        vault.remove_key("old");
+        assert_eq!(vault.principal("old"), None);
+    }
+
+    #[test]
+    fn test_nonce() {
+        let nonces = Nonces::new();
+        let nonce = nonces.generate();
+        assert_eq!(nonces.validate(&nonce), NonceState::Valid);
+
+        let mut tampered = nonce.into_bytes();
+        tampered[2] = if tampered[2] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert_eq!(nonces.validate(&tampered), NonceState::Invalid);
+        assert_eq!(nonces.validate("not a nonce"), NonceState::Invalid);
+
+        let mut other = Nonces::new();
+        assert_eq!(other.validate(&nonces.generate()), NonceState::Invalid);
+
+        other.opaque_secret = Some(b"opaque".as_slice().into());
+        let nonce = other.generate();
+        let opaque = other.opaque(&nonce);
+        assert!(other.validate_opaque(&nonce, &opaque));
+        assert!(!other.validate_opaque("other nonce", &opaque));
+        assert!(!other.validate_opaque(&nonce, "not an opaque"));
+        assert!(nonces.validate_opaque(&nonce, "anything"));
+
+        other.window = Duration::ZERO;
+        let nonce = other.generate();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(other.validate(&nonce), NonceState::Stale);
+    }
+
+    #[test]
+    fn test_digest_check() {
+        let mut nonces = Nonces::new();
+        let request = || {
+            Request::builder()
+                .method(Method::GET)
+                .uri("/api")
+                .body(Body::empty())
+                .unwrap()
+        };
+        // issue a nonce as a challenge does, and answer it
+        let answer = |nonces: &Nonces, password: &str| {
+            let response = unauthorized(nonces, "", "srv", false);
+            let challenge = response.headers()[header::WWW_AUTHENTICATE]
+                .to_str()
+                .unwrap()
+                .to_string();
+            let mut auth = Authorization::from_header(challenge).unwrap();
+            auth.username = "user".to_string();
+            auth.uri = "/api".to_string();
+            auth.nc = "00000001".to_string();
+            auth.cnonce = "cnonce".to_string();
+            auth.response = auth.digest("user", password, &Method::GET);
+            auth
+        };
+        let stale = |response: &axum::response::Response| {
+            response.headers()[header::WWW_AUTHENTICATE]
+                .to_str()
+                .unwrap()
+                .ends_with("stale=true")
+        };
+
+        let auth = answer(&nonces, "pass");
+        assert!(auth
+            .check("user", "pass", nonces.clone(), request(), "srv")
+            .is_ok());
+        // the nonce can't be used again
+        let response = auth
+            .check("user", "pass", nonces.clone(), request(), "srv")
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!stale(&response));
+
+        let auth = answer(&nonces, "wrong");
+        let response = auth
+            .check("user", "pass", nonces.clone(), request(), "srv")
+            .unwrap_err();
+        assert!(!stale(&response));
+
+        nonces.window = Duration::ZERO;
+        let valid = answer(&nonces, "pass");
+        let tampered = answer(&nonces, "wrong");
+        std::thread::sleep(Duration::from_millis(5));
+        let response = valid
+            .check("user", "pass", nonces.clone(), request(), "srv")
+            .unwrap_err();
+        assert!(stale(&response));
+        let response = tampered
+            .check("user", "pass", nonces.clone(), request(), "srv")
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!stale(&response));
+    }
+
+    #[test]
+    fn test_bearer_auth() {
+        let mut auth = BearerAuth::new("secret");
+        let request = |authorization: Option<&str>| {
+            let mut request = Request::builder();
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = auth.check(request(None)).unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        assert!(auth.check(request(Some("Bearer other"))).is_err());
+        assert!(auth.check(request(Some("Basic c2VjcmV0"))).is_err());
+        assert!(auth.check(request(Some("Bearer secret"))).is_ok());
+    }
+
+    #[test]
+    fn test_query_param_auth() {
+        let mut auth = QueryParamAuth::new("token", |t: &str| t == "a+b");
+        let request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = auth.check(request("/download?file=1")).unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = auth.check(request("/download?token=bad")).unwrap_err();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // `+` is a space in query string
+        let response = auth.check(request("/download?token=a+b")).unwrap_err();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let passed = auth.check(request("/download?token=a%2Bb")).unwrap();
+        assert_eq!(passed.uri(), "/download");
+        let passed = auth
+            .check(request("/download?file=1&token=a%2Bb&token=leak&x=%20"))
+            .unwrap();
+        assert_eq!(passed.uri(), "/download?file=1&x=%20");
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{drain_connections, SpaServer, SpaStatic, DEFAULT_SHUTDOWN_TIMEOUT};
+use anyhow::{anyhow, Result};
+use axum_server::Handle;
+use std::{sync::mpsc, thread::JoinHandle};
+use tokio::sync::oneshot;
+
+/// A [SpaServer] running in a background thread, see [SpaServer::run_in_background]
+///
+/// Dropping it without calling [stop](Self::stop) also shutdown the server, but
+/// the background thread will be detached.
+pub struct BackgroundServer {
+    thread: JoinHandle<Result<()>>,
+    shutdown: oneshot::Sender<()>,
+    port: u16,
+}
+
+impl BackgroundServer {
+    pub(crate) fn spawn<T, Root>(srv: SpaServer<T>, root: Root) -> Result<Self>
+    where
+        T: Clone + Send + Sync + 'static,
+        Root: SpaStatic + Send + 'static,
+    {
+        let handle: Handle = srv.serve.handle.clone();
+        let timeout = srv
+            .serve
+            .shutdown_timeout
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let (port_tx, port_rx) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()?;
+            runtime.block_on(async move {
+                let listening = handle.clone();
+                tokio::spawn(async move {
+                    if let Some(addr) = listening.listening().await {
+                        let _ = port_tx.send(addr.port());
+                    }
+                });
+                tokio::spawn(async move {
+                    // sender dropped also means shutdown
+                    let _ = shutdown_rx.await;
+                    drain_connections(handle, timeout).await;
+                });
+
+                srv.run(root).await
+            })
+        });
+
+        match port_rx.recv() {
+            Ok(port) => Ok(Self {
+                thread,
+                shutdown,
+                port,
+            }),
+            // server exited before listening, return its error
+            Err(_) => Err(thread
+                .join()
+                .map_err(|_| anyhow!("background server thread panicked"))?
+                .err()
+                .unwrap_or_else(|| anyhow!("background server exited before listening"))),
+        }
+    }
+
+    /// The actual bound port, useful when [port](SpaServer::port) is `0`
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Shutdown the server gracefully, and wait for the background thread exit
+    pub fn stop(self) -> Result<()> {
+        let _ = self.shutdown.send(());
+        self.thread
+            .join()
+            .map_err(|_| anyhow!("background server thread panicked"))?
+    }
+}
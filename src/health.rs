@@ -0,0 +1,75 @@
+//! Composite health endpoint, see [SpaServer::health_check_with_dependencies](crate::SpaServer::health_check_with_dependencies)
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use futures_util::future::{join_all, BoxFuture};
+use std::{fmt::Write, sync::Arc, time::Duration};
+
+/// A dependency checked by the health endpoint, such as database or cache
+pub struct HealthCheck {
+    pub name: String,
+    /// resolves to `true` if the dependency is available
+    pub check: Box<dyn Fn() -> BoxFuture<'static, bool> + Send + Sync>,
+}
+
+/// escape `s` as a json string
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Register the health endpoint into `router`, checks not finished in `timeout` are failed
+pub(crate) fn register(
+    router: Router,
+    path: &str,
+    checks: Vec<HealthCheck>,
+    timeout: Duration,
+) -> Router {
+    let checks = Arc::new(checks);
+    router.route(
+        path,
+        get(move || async move {
+            let results = join_all(checks.iter().map(|c| async move {
+                let passed = tokio::time::timeout(timeout, (c.check)())
+                    .await
+                    .unwrap_or(false);
+                (c.name.as_str(), passed)
+            }))
+            .await;
+
+            let passed = results.iter().filter(|(_, passed)| *passed).count();
+            let (status, code) = if passed == results.len() {
+                ("healthy", StatusCode::OK)
+            } else if passed == 0 {
+                ("unhealthy", StatusCode::SERVICE_UNAVAILABLE)
+            } else {
+                ("degraded", StatusCode::MULTI_STATUS)
+            };
+            let dependencies = results
+                .iter()
+                .map(|(name, passed)| format!("{}:{}", json_string(name), passed))
+                .collect::<Vec<_>>()
+                .join(",");
+            let body = format!(
+                r#"{{"status":"{}","dependencies":{{{}}}}}"#,
+                status, dependencies
+            );
+            (code, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+        }),
+    )
+}
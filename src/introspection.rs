@@ -0,0 +1,43 @@
+//! Routes registered by [SpaServer](crate::SpaServer), for detecting conflicts and listing
+//! them for debugging, see [SpaServer::route_conflict_detection](crate::SpaServer::route_conflict_detection).
+//!
+//! axum doesn't expose the routes inside a [Router], so [SpaServer](crate::SpaServer) records
+//! them by itself when registering. Methods of the routes inside a nested router are unknown,
+//! they are reported as `ANY`.
+#[cfg(feature = "debug")]
+use axum::{routing::get, Json, Router};
+
+/// A route registered by [SpaServer](crate::SpaServer)
+pub(crate) struct RouteInfo {
+    pub(crate) path: String,
+    pub(crate) methods: Vec<&'static str>,
+    /// versions redirected to by the `latest` route of [route_versioned](crate::SpaServer::route_versioned)
+    #[cfg_attr(not(feature = "debug"), allow(dead_code))]
+    pub(crate) versions: Vec<u32>,
+}
+
+impl RouteInfo {
+    /// whether a route of `path` and `methods` can't be registered together with this one
+    pub(crate) fn conflicts(&self, path: &str, methods: &[&'static str]) -> bool {
+        self.path == path
+            && (self.methods.contains(&"ANY")
+                || methods.contains(&"ANY")
+                || methods.iter().any(|m| self.methods.contains(m)))
+    }
+}
+
+/// Register the route list endpoint into `router`
+#[cfg(feature = "debug")]
+pub(crate) fn register(router: Router, path: &str, routes: Vec<RouteInfo>) -> Router {
+    let routes: Vec<_> = routes
+        .into_iter()
+        .map(|r| match r.versions.is_empty() {
+            true => serde_json::json!({ "path": r.path, "methods": r.methods }),
+            false => {
+                serde_json::json!({ "path": r.path, "methods": r.methods, "versions": r.versions })
+            }
+        })
+        .collect();
+    let routes = serde_json::Value::Array(routes);
+    router.route(path, get(move || async move { Json(routes) }))
+}
@@ -33,6 +33,12 @@
 //! # Session
 //! See [session] module for more detail.
 //!
+//! # SPA fallback
+//! Not found requests get `index.html` only if they accept `text/html` and are not under `/api`,
+//! so API clients and `fetch` requests get a real `404 Not Found`, see
+//! [default_spa_fallback_condition]. Earlier versions served `index.html` for every not found
+//! request, use `.spa_fallback_condition(|_| true)` to keep that.
+//!
 //! # Dev
 //! When writing SPA application, you may want use hot-reload functionallity provided
 //! by SPA framework. such as [`vite dev`] or [`ng serve`].
@@ -46,34 +52,42 @@
 //! ```
 use anyhow::{anyhow, Context, Result};
 use axum::{
+    body::Body,
     body::Bytes,
     body::HttpBody,
     extract::{Host, Request},
+    handler::{Handler, HandlerWithoutStateExt},
     http::HeaderValue,
-    response::Response,
-    routing::{any, get_service, Route},
+    response::{IntoResponse, Response},
+    routing::{any, get_service, MethodRouter, Route},
 };
 #[cfg(feature = "openssl")]
 use axum_server::tls_openssl::OpenSSLConfig;
-#[cfg(feature = "rustls")]
-use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use futures_util::future::try_join_all;
+#[cfg(feature = "reverse-proxy")]
+use http::Uri;
 use http::{
-    header::{self},
-    StatusCode,
+    header::{self, HeaderName},
+    HeaderMap, Method, StatusCode,
 };
-#[cfg(feature = "reverse-proxy")]
-use http::{Method, Uri};
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
+use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
 use std::{
     collections::HashMap,
     convert::Infallible,
     env::current_exe,
     fs::{self, create_dir_all},
-    net::SocketAddr,
+    future::Future,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6},
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
+use tokio::sync::oneshot;
 use tower::{Layer, Service, ServiceExt as TowerServiceExt};
 use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     services::{ServeDir, ServeFile},
     set_header::SetResponseHeaderLayer,
 };
@@ -83,10 +97,37 @@ pub mod rust_embed {
 }
 
 pub use axum::*;
+#[cfg(feature = "acme")]
+mod acme;
 pub mod auth;
+mod background;
+mod health;
+mod introspection;
+#[cfg(feature = "hot-reload")]
+mod live_reload;
+pub mod middleware;
+mod minify;
+mod once;
+mod options;
+mod pid_file;
+#[cfg(feature = "config-reload")]
+mod reload;
 pub mod session;
+#[cfg(feature = "swagger-ui")]
+mod swagger;
+#[cfg(feature = "rustls")]
+mod tls;
+mod type_map;
+#[cfg(feature = "ws-proxy")]
+mod ws_proxy;
 pub use axum::debug_handler;
 pub use axum_help::*;
+pub use background::BackgroundServer;
+pub use futures_util::future::BoxFuture;
+pub use health::HealthCheck;
+#[cfg(feature = "rustls")]
+pub use tls::SniRoute;
+pub use type_map::TypeMap;
 
 /// A server wrapped axum server.
 ///
@@ -94,24 +135,221 @@ pub use axum_help::*;
 /// - serve static files in SPA root path
 /// - serve API requests in router
 /// - fallback to SPA static file when route matching failed
-///     - if still get 404, it will redirect to SPA index.html
+///     - if still get 404, it will redirect to SPA index.html, see [SpaServer::spa_fallback_condition]
 ///
 #[derive(Default)]
 pub struct SpaServer<T = ()>
 where
     T: Clone + Send + Sync + 'static,
 {
-    static_path: Vec<(String, PathBuf)>,
+    static_path: Vec<StaticPath>,
     port: u16,
     main_router: Router,
     api_router: Router,
     data: Option<T>,
     forward: Option<String>,
     release_path: PathBuf,
-    extra_layer: Vec<Box<dyn FnOnce(Router) -> Router>>,
+    extra_layer: Vec<RouterLayer>,
     host_routers: HashMap<String, Router>,
+    layers: options::LayerOptions,
+    routing: options::RoutingOptions,
+    statics: options::StaticOptions,
+    http: options::HttpOptions,
+    health: options::HealthOptions,
+    serve: options::ServeOptions,
+}
+
+type RouterLayer = Box<dyn FnOnce(Router) -> Router + Send>;
+
+type RouterLayerFactory = Box<dyn FnOnce() -> BoxFuture<'static, Result<RouterLayer>> + Send>;
+
+/// Build a layer asynchronously when server starts, see [SpaServer::layer_with_factory]
+///
+/// It's useful for layers need expensive initialization, such as opening database
+/// connections, which should not happen when configuring the server.
+///
+/// ## Example
+/// ```no_run
+/// # use spa_rs::{async_trait, LayerFactory, Extension};
+/// struct PoolFactory;
+///
+/// #[async_trait]
+/// impl LayerFactory for PoolFactory {
+///     type Layer = Extension<String>;
+///
+///     async fn build(self) -> anyhow::Result<Self::Layer> {
+///         // connect to database here
+///         Ok(Extension("pool".to_string()))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait LayerFactory: Send + 'static {
+    /// the layer built, with the same bounds as [SpaServer::layer]
+    type Layer;
+
+    /// called once in `run`, before the server starts listening
+    async fn build(self) -> Result<Self::Layer>;
+}
+
+type ErrorHandler = Arc<dyn Fn(StatusCode, String) -> Response + Send + Sync>;
+
+fn error_response(handler: Option<ErrorHandler>, status: StatusCode, message: String) -> Response {
+    match handler {
+        Some(handler) => handler(status, message),
+        None => (status, message).into_response(),
+    }
+}
+
+/// Default condition of SPA `index.html` fallback, see [SpaServer::spa_fallback_condition]
+///
+/// Returns `true` when `Accept` header includes `text/html`, and the path is not under `/api`.
+pub fn default_spa_fallback_condition(request: &Request) -> bool {
+    let accept_html = request
+        .headers()
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.contains("text/html"));
+    accept_html && !middleware::under_prefix(request.uri().path(), "/api")
+}
+
+/// Options of listening sockets bound by socket2
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SocketOptions {
+    reuse_port: bool,
+    keepalive: Option<(Duration, u32)>,
+    backlog: Option<u32>,
+    recv_buffer_size: Option<u32>,
+    send_buffer_size: Option<u32>,
+}
+
+impl SocketOptions {
+    /// apply the options to `socket`, bind it to `addr` and listen
+    fn listen(&self, socket: Socket, addr: SocketAddr) -> Result<std::net::TcpListener> {
+        socket.set_reuse_address(true)?;
+        if self.reuse_port {
+            Self::set_reuse_port(&socket)?;
+        }
+        // accepted connections inherit keepalive options from the listening socket
+        if let Some((interval, retries)) = self.keepalive {
+            let keepalive = TcpKeepalive::new()
+                .with_time(interval)
+                .with_interval(interval);
+            #[cfg(not(windows))]
+            let keepalive = keepalive.with_retries(retries);
+            #[cfg(windows)]
+            let _ = retries;
+            socket
+                .set_tcp_keepalive(&keepalive)
+                .context("set SO_KEEPALIVE error")?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket
+                .set_recv_buffer_size(size as usize)
+                .context("set SO_RCVBUF error")?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket
+                .set_send_buffer_size(size as usize)
+                .context("set SO_SNDBUF error")?;
+        }
+        socket
+            .bind(&addr.into())
+            .with_context(|| format!("bind address {} error", addr))?;
+        let backlog = self.backlog.unwrap_or(1024);
+        socket.listen(backlog.try_into().unwrap_or(i32::MAX))?;
+        Ok(socket.into())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_reuse_port(socket: &Socket) -> Result<()> {
+        socket
+            .set_reuse_port(true)
+            .context("set SO_REUSEPORT error")
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn set_reuse_port(_socket: &Socket) -> Result<()> {
+        warn!("SO_REUSEPORT is only supported on linux, ignored");
+        Ok(())
+    }
+}
+
+/// Runtime static file path, see [SpaServer::static_path]
+struct StaticPath {
+    path: String,
+    dir: PathBuf,
+    fallthrough_for: Vec<mime::Mime>,
+    cache_rules: middleware::CacheRuleSet,
+    index_file: Option<String>,
+}
+
+impl StaticPath {
+    fn new(path: String, dir: PathBuf) -> Self {
+        Self {
+            path,
+            dir,
+            fallthrough_for: Vec::new(),
+            cache_rules: middleware::CacheRuleSet::new(),
+            index_file: None,
+        }
+    }
+}
+
+/// Serve `index` instead of `index.html` for directory requests, by appending it to paths
+/// ending with `/` before `service`
+fn with_index_file<S>(
+    service: S,
+    index: Option<String>,
+) -> tower::util::MapRequest<S, impl FnMut(Request) -> Request + Clone> {
+    tower::util::MapRequest::new(service, move |mut request: Request| {
+        let Some(index) = &index else {
+            return request;
+        };
+        if request.uri().path().ends_with('/') {
+            let path_and_query = match request.uri().query() {
+                Some(query) => format!("{}{}?{}", request.uri().path(), index, query),
+                None => format!("{}{}", request.uri().path(), index),
+            };
+            let mut parts = request.uri().clone().into_parts();
+            if let Ok(path_and_query) = path_and_query.parse() {
+                parts.path_and_query = Some(path_and_query);
+                if let Ok(uri) = http::Uri::from_parts(parts) {
+                    *request.uri_mut() = uri;
+                }
+            }
+        }
+        request
+    })
+}
+
+/// mime type detected from extension of `path`, `text/html` if there is no extension
+fn path_mime(path: &str) -> mime::Mime {
+    match Path::new(path).extension() {
+        Some(_) => mime_guess::from_path(path).first_or_octet_stream(),
+        None => mime::TEXT_HTML,
+    }
+}
+
+/// Options of [SpaServer] still needed after the router is built
+struct ServeParts {
+    port: u16,
+    #[cfg(feature = "acme")]
+    release_path: PathBuf,
+    serve: options::ServeOptions,
 }
 
+/// Header of [SpaServer::request_id] if not configured by [SpaServer::request_id_header]
+pub const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
+/// Default max time waiting for each dependency of [SpaServer::health_check_with_dependencies]
+pub const DEFAULT_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default max time waiting for in-flight requests when shutdown gracefully
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Path of ACME HTTP-01 challenge files
+pub const ACME_CHALLENGE_PATH: &str = "/.well-known/acme-challenge";
+
 #[cfg(feature = "reverse-proxy")]
 async fn forwarded_to_dev(
     Extension(forward_addr): Extension<String>,
@@ -159,11 +397,16 @@ where
     T: Clone + Send + Sync + 'static,
 {
     /// Just new(), nothing special
+    ///
+    /// Note that not found requests only fall back to `index.html` if they accept `text/html`
+    /// and are not under `/api`, see [spa_fallback_condition](Self::spa_fallback_condition).
     pub fn new() -> Result<Self> {
         Ok(Self {
             static_path: Vec::new(),
             port: 8080,
             main_router: Router::new(),
+            api_router: Router::new(),
+            data: None,
             forward: None,
             release_path: current_exe()?
                 .parent()
@@ -171,8 +414,12 @@ where
                 .join(format!(".{}_static_files", env!("CARGO_PKG_NAME"))),
             extra_layer: Vec::new(),
             host_routers: HashMap::new(),
-            api_router: Router::new(),
-            data: None,
+            layers: Default::default(),
+            routing: Default::default(),
+            statics: Default::default(),
+            http: Default::default(),
+            health: Default::default(),
+            serve: Default::default(),
         })
     }
 
@@ -187,6 +434,33 @@ where
         self
     }
 
+    /// Transform the data set by [data](Self::data) into another type, routes and layers are kept
+    ///
+    /// `f` is not called if no data is set. It's useful when composing the server from modules,
+    /// such as `base_server.map_data(AppState::from_config)`.
+    pub fn map_data<U>(self, f: impl FnOnce(T) -> U) -> SpaServer<U>
+    where
+        U: Clone + Send + Sync + 'static,
+    {
+        SpaServer {
+            static_path: self.static_path,
+            port: self.port,
+            main_router: self.main_router,
+            api_router: self.api_router,
+            data: self.data.map(f),
+            forward: self.forward,
+            release_path: self.release_path,
+            extra_layer: self.extra_layer,
+            host_routers: self.host_routers,
+            layers: self.layers,
+            routing: self.routing,
+            statics: self.statics,
+            http: self.http,
+            health: self.health,
+            serve: self.serve,
+        }
+    }
+
     /// Specific an axum layer to server
     ///
     /// This is similar to [axum middleware](https://docs.rs/axum/latest/axum/#middleware)
@@ -205,6 +479,410 @@ where
         self
     }
 
+    /// Same as [layer](Self::layer), but the layer is built by `factory` when server starts
+    ///
+    /// `run` returns error if any factory failed. Layers of factories wrap the ones added by
+    /// [layer](Self::layer), in the order they are added.
+    pub fn layer_with_factory<F, NewResBody>(mut self, factory: F) -> Self
+    where
+        F: LayerFactory,
+        F::Layer: Layer<Route> + Clone + Send + 'static,
+        <F::Layer as Layer<Route>>::Service: Service<Request, Response = Response<NewResBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        <<F::Layer as Layer<Route>>::Service as Service<Request>>::Future: Send + 'static,
+        NewResBody: HttpBody<Data = Bytes> + Send + 'static,
+        NewResBody::Error: Into<BoxError>,
+    {
+        self.layers.factories.push(Box::new(move || {
+            Box::pin(async move {
+                let layer = factory.build().await?;
+                Ok(Box::new(move |app: Router| app.layer(layer)) as RouterLayer)
+            })
+        }));
+        self
+    }
+
+    /// Specific a handler to build the response when a route matched but not for the
+    /// requested method, instead of the empty `405 Method Not Allowed`
+    ///
+    /// The handler is called with a copy of the request without body, the methods allowed
+    /// can be accessed by [AllowedMethods](middleware::AllowedMethods) extension.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{SpaServer, Extension, http::{Method, StatusCode, Uri}, middleware::AllowedMethods};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.method_not_allowed_handler(
+    ///     |method: Method, uri: Uri, Extension(allowed): Extension<AllowedMethods>| async move {
+    ///         (
+    ///             StatusCode::METHOD_NOT_ALLOWED,
+    ///             format!("{} {} is not allowed, try {:?}", method, uri.path(), allowed.0),
+    ///         )
+    ///     },
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn method_not_allowed_handler<H, X>(self, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.layer(axum::middleware::from_fn(
+            move |request: Request, next: axum::middleware::Next| {
+                middleware::method_not_allowed(handler.clone(), request, next)
+            },
+        ))
+    }
+
+    /// Log requests taking longer than `threshold` with `warn!`
+    ///
+    /// Use [SlowRequestDetectorLayer](middleware::SlowRequestDetectorLayer) with [layer](Self::layer)
+    /// for a custom callback, such as recording metrics.
+    pub fn warn_slow_requests(self, threshold: Duration) -> Self {
+        self.layer(middleware::SlowRequestDetectorLayer::new(
+            threshold,
+            middleware::warn_slow_request,
+        ))
+    }
+
+    /// Assign a uuid to requests without `X-Request-Id` header, and echo it in the response
+    ///
+    /// Handlers can read it from the request header, or by `Extension<RequestId>`
+    /// of [tower_http::request_id].
+    pub fn request_id(self) -> Self {
+        self.request_id_header(DEFAULT_REQUEST_ID_HEADER)
+    }
+
+    /// Same as [request_id](Self::request_id), but use `header` such as `X-Correlation-Id`
+    ///
+    /// `run` returns error if `header` is not a valid header name.
+    pub fn request_id_header(mut self, header: &str) -> Self {
+        self.http.request_id_header = Some(header.to_string());
+        self
+    }
+
+    /// Add `Server-Timing: total;dur=<ms>` to every response, so the request duration shows
+    /// in browser devtools
+    pub fn enable_server_timing(mut self, enable: bool) -> Self {
+        self.http.server_timing = enable;
+        self
+    }
+
+    /// Limit size of responses of `path` and paths under it to `max_bytes`
+    ///
+    /// Responses known to be larger are replaced with `500 Internal Server Error`, streaming ones
+    /// are aborted, see [ResponseSizeLimitLayer](middleware::ResponseSizeLimitLayer). It can be
+    /// called multiple times, the limit of the longest matching path applies.
+    pub fn response_size_limit_for(mut self, path: &str, max_bytes: usize) -> Self {
+        self.http
+            .response_size_limits
+            .push((self.prefixed(path), max_bytes));
+        self
+    }
+
+    /// Protect a flapping backend behind `path_prefix`, such as a reverse proxy path, with a
+    /// circuit breaker
+    ///
+    /// Requests get `503 Service Unavailable` immediately while the circuit is open, see
+    /// [CircuitBreakerLayer](middleware::CircuitBreakerLayer).
+    pub fn circuit_breaker(
+        self,
+        path_prefix: &str,
+        config: middleware::CircuitBreakerConfig,
+    ) -> Self {
+        let layer =
+            middleware::CircuitBreakerLayer::new(config).path_prefix(&self.prefixed(path_prefix));
+        self.layer(layer)
+    }
+
+    /// Transform body of responses whose `Content-Type` matches `content_type_filter`, such as
+    /// injecting a script tag into html pages
+    ///
+    /// `*` subtype matches all subtypes, such as `text/*`, see
+    /// [ContentTypeFilter](filter::ContentTypeFilter). Only `200 OK` responses of non-`HEAD`
+    /// requests are transformed, they are buffered into memory entirely before `transform`,
+    /// responses larger than `max_bytes` are passed through untransformed. Compressed responses
+    /// are not transformed, add this before [response_compression](Self::response_compression).
+    /// `ETag`, `Last-Modified` and `Accept-Ranges` are removed if the body is changed, they
+    /// describe the original one.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{SpaServer, body::Bytes};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.response_body_transform(mime::TEXT_HTML, 1 << 20, |body| {
+    ///     let html = String::from_utf8_lossy(&body)
+    ///         .replace("</body>", "<script src=\"/analytics.js\"></script></body>");
+    ///     Bytes::from(html)
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn response_body_transform(
+        self,
+        content_type_filter: mime::Mime,
+        max_bytes: usize,
+        transform: fn(Bytes) -> Bytes,
+    ) -> Self {
+        let filter = filter::ContentTypeFilter::new(vec![content_type_filter]);
+        self.layer(axum::middleware::map_response(
+            move |method: Method, response: Response| {
+                let filter = filter.clone();
+                async move {
+                    let matched = response
+                        .headers()
+                        .get(header::CONTENT_TYPE)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<mime::Mime>().ok())
+                        .is_some_and(|m| filter.is_allowed(&m));
+                    if !matched
+                        || method == Method::HEAD
+                        || response.status() != StatusCode::OK
+                        || response.headers().contains_key(header::CONTENT_ENCODING)
+                    {
+                        return response;
+                    }
+
+                    let (mut parts, body) = response.into_parts();
+                    let original = match middleware::try_buffer_body(body, max_bytes).await {
+                        Ok(middleware::Buffered::Full(body)) => body,
+                        Ok(middleware::Buffered::TooLarge { body, .. }) => {
+                            return Response::from_parts(parts, body)
+                        }
+                        Err(e) => {
+                            return (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                format!("read response body error: {}", e),
+                            )
+                                .into_response()
+                        }
+                    };
+                    let body = transform(original.clone());
+                    if body != original {
+                        for name in [header::ETAG, header::LAST_MODIFIED, header::ACCEPT_RANGES] {
+                            parts.headers.remove(name);
+                        }
+                    }
+                    parts.headers.remove(header::TRANSFER_ENCODING);
+                    parts
+                        .headers
+                        .insert(header::CONTENT_LENGTH, body.len().into());
+                    Response::from_parts(parts, Body::from(body))
+                }
+            },
+        ))
+    }
+
+    /// Log request bodies of `path_prefix` and paths under it with `logger`, other requests are
+    /// untouched, so endpoints handling passwords or other sensitive data never get logged
+    ///
+    /// Body is buffered and handed to the handler unchanged, `logger` receives a copy of it and
+    /// the request with an empty body for metadata such as method or headers. `logger` is called
+    /// in a spawned task, so it never blocks the response. Bodies larger than `max_bytes` are
+    /// streamed to the handler without logging. It can be called multiple times for different
+    /// paths.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::SpaServer;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.request_body_logging_for("/orders", 4096, |body, request| {
+    ///     log::info!("{} {}: {:?}", request.method(), request.uri(), body);
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn request_body_logging_for<F>(self, path_prefix: &str, max_bytes: usize, logger: F) -> Self
+    where
+        F: Fn(Bytes, &Request) + Send + Sync + 'static,
+    {
+        let prefix = self.prefixed(path_prefix).trim_end_matches('/').to_string();
+        let logger = Arc::new(logger);
+        self.layer(axum::middleware::map_request(move |request: Request| {
+            let prefix = prefix.clone();
+            let logger = logger.clone();
+            async move {
+                if !middleware::under_prefix(request.uri().path(), &prefix) {
+                    return Ok::<_, (StatusCode, String)>(request);
+                }
+
+                let (parts, body) = request.into_parts();
+                let body = match middleware::try_buffer_body(body, max_bytes).await {
+                    Ok(middleware::Buffered::Full(body)) => body,
+                    // too large to log, hand it to the handler as is
+                    Ok(middleware::Buffered::TooLarge { body, .. }) => {
+                        return Ok(Request::from_parts(parts, body))
+                    }
+                    Err(e) => {
+                        return Err((
+                            StatusCode::BAD_REQUEST,
+                            format!("read request body error: {}", e),
+                        ))
+                    }
+                };
+                let metadata = Request::from_parts(parts.clone(), Body::empty());
+                let logged = body.clone();
+                tokio::spawn(async move { logger(logged, &metadata) });
+
+                Ok(Request::from_parts(parts, Body::from(body)))
+            }
+        }))
+    }
+
+    /// Specific an axum layer which runs before authentication of [route_with_auth](Self::route_with_auth)
+    ///
+    /// It wraps all routes like [layer](Self::layer), but inside layers added by
+    /// [layer](Self::layer) which are applied last (outermost).
+    pub fn layer_before_auth<L, NewResBody>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request, Response = Response<NewResBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+        NewResBody: HttpBody<Data = Bytes> + Send + 'static,
+        NewResBody::Error: Into<BoxError>,
+    {
+        self.layers
+            .before_auth
+            .push(Box::new(move |app| app.layer(layer)));
+        self
+    }
+
+    /// Specific an axum layer which runs after authentication of [route_with_auth](Self::route_with_auth)
+    /// passed, such as a layer reading the authenticated user
+    ///
+    /// It's only applied to routes added by [route_with_auth](Self::route_with_auth), no matter
+    /// which one is called first.
+    pub fn layer_after_auth<L, NewResBody>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request, Response = Response<NewResBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+        NewResBody: HttpBody<Data = Bytes> + Send + 'static,
+        NewResBody::Error: Into<BoxError>,
+    {
+        self.layers
+            .after_auth
+            .push(Box::new(move |router| router.layer(layer.clone())));
+        self
+    }
+
+    /// Add `headers` to all responses, unless the header is already set by handler
+    ///
+    /// All headers are inserted in a single layer.
+    pub fn default_headers(self, headers: HeaderMap) -> Self {
+        self.layer(axum::middleware::map_response(
+            move |mut response: Response| {
+                let headers = headers.clone();
+                async move {
+                    for name in headers.keys() {
+                        if !response.headers().contains_key(name) {
+                            for value in headers.get_all(name) {
+                                response.headers_mut().append(name, value.clone());
+                            }
+                        }
+                    }
+                    response
+                }
+            },
+        ))
+    }
+
+    /// Add `headers` to all responses, replacing the ones set by handler
+    pub fn override_headers(self, headers: HeaderMap) -> Self {
+        self.layer(axum::middleware::map_response(
+            move |mut response: Response| {
+                let headers = headers.clone();
+                async move {
+                    for name in headers.keys() {
+                        response.headers_mut().remove(name);
+                        for value in headers.get_all(name) {
+                            response.headers_mut().append(name, value.clone());
+                        }
+                    }
+                    response
+                }
+            },
+        ))
+    }
+
+    /// Add `Secure` attribute to all `Set-Cookie` headers which miss it
+    ///
+    /// Intended for servers running with TLS, so cookies are never sent over plain http.
+    pub fn https_only_cookies(self) -> Self {
+        self.cookie_attribute("Secure")
+    }
+
+    /// Add `HttpOnly` attribute to all `Set-Cookie` headers which miss it, so cookies can not
+    /// be read by javascript
+    pub fn http_only_cookies(self) -> Self {
+        self.cookie_attribute("HttpOnly")
+    }
+
+    fn cookie_attribute(self, attribute: &'static str) -> Self {
+        self.layer(axum::middleware::map_response(
+            move |mut response: Response| async move {
+                session::add_cookie_attribute(response.headers_mut(), attribute);
+                response
+            },
+        ))
+    }
+
+    /// Compress responses with brotli, zstd or gzip, according to `Accept-Encoding` of request
+    pub fn response_compression(self) -> Self {
+        self.response_compression_with_options(middleware::CompressionOptions::new())
+    }
+
+    /// Same as [response_compression](Self::response_compression), with specific quality
+    pub fn response_compression_with_options(
+        mut self,
+        options: middleware::CompressionOptions,
+    ) -> Self {
+        self.extra_layer
+            .push(Box::new(move |app| options.apply(app)));
+        self
+    }
+
+    /// Same as [response_compression](Self::response_compression), with specific gzip level
+    pub fn gzip_compression_level(self, level: u32) -> Self {
+        self.response_compression_with_options(
+            middleware::CompressionOptions::new().gzip_level(level),
+        )
+    }
+
+    /// Compress text files (html, js, css, etc.) of SPA root into `.br` files with `quality`
+    /// after released, they are served to clients accepting brotli
+    #[cfg(feature = "brotli")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "brotli")))]
+    pub fn precompressed_brotli_quality(mut self, quality: u32) -> Self {
+        self.statics.precompressed_br = Some(quality.min(11));
+        self
+    }
+
+    /// Decompress request bodies with `Content-Encoding` of `gzip`, `br` or `deflate`
+    /// before handlers run
+    ///
+    /// `Content-Encoding` header is removed after decompressed, and requests with unknown encoding
+    /// get `415 Unsupported Media Type`.
+    pub fn request_decompression(self) -> Self {
+        self.layer(middleware::RequestDecompressionLayer::new())
+    }
+
+    /// Propagate [W3C Trace Context](https://www.w3.org/TR/trace-context/) headers
+    ///
+    /// See [TraceContextLayer](middleware::TraceContextLayer) for more detail.
+    pub fn with_tracing_propagation(self) -> Self {
+        self.layer(middleware::TraceContextLayer::new())
+    }
+
     /// make a reverse proxy which redirect all SPA requests to dev server, such as `ng serve`, `vite`.  
     ///
     /// it's useful when debugging UI
@@ -215,6 +893,28 @@ where
         self
     }
 
+    /// Remove `headers` from responses of [reverse_proxy](Self::reverse_proxy) and
+    /// [proxy_websocket](Self::proxy_websocket), such as `Server`
+    ///
+    /// See [ProxyHeadersLayer](middleware::ProxyHeadersLayer).
+    #[cfg(any(feature = "reverse-proxy", feature = "ws-proxy"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "reverse-proxy", feature = "ws-proxy"))))]
+    pub fn proxy_response_headers_strip(mut self, headers: Vec<HeaderName>) -> Self {
+        self.routing.proxy_headers = self.routing.proxy_headers.strip_response(headers);
+        self
+    }
+
+    /// Set `headers` into requests forwarded by [reverse_proxy](Self::reverse_proxy) and
+    /// [proxy_websocket](Self::proxy_websocket), such as `X-Forwarded-Proto`
+    ///
+    /// They are sent to the websocket upstream no matter [forward_headers](Self::forward_headers).
+    #[cfg(any(feature = "reverse-proxy", feature = "ws-proxy"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "reverse-proxy", feature = "ws-proxy"))))]
+    pub fn proxy_request_headers_add(mut self, headers: HeaderMap) -> Self {
+        self.routing.proxy_headers = self.routing.proxy_headers.add_request(headers);
+        self
+    }
+
     /// static file release path in runtime
     ///
     /// Default path is /tmp/[env!(CARGO_PKG_NAME)]_static_files
@@ -228,153 +928,1384 @@ where
     where
         Root: SpaStatic,
     {
-        self.run_raw(Some(root), None).await
+        self.run_raw(Some(root), None, None).await
     }
 
-    /// Run the spa server with tls
-    #[cfg(any(feature = "openssl", feature = "rustls"))]
-    pub async fn run_tls<Root>(self, root: Root, config: HttpsConfig) -> Result<()>
+    /// Run the spa server until exactly one connection is served, such as in acceptance tests
+    /// or one-shot scripts
+    ///
+    /// The server stops accepting after the first connection, and returns once the connection
+    /// is closed. Keep-alive connections are closed after the in-flight request finished.
+    pub async fn run_once<Root>(mut self, root: Root) -> Result<()>
     where
         Root: SpaStatic,
     {
-        self.run_raw(Some(root), Some(config)).await
+        self.serve.serve_once = true;
+        self.run_raw(Some(root), None, None).await
     }
 
-    /// Run the spa server without spa root
-    pub async fn run_api(self) -> Result<()> {
-        self.run_raw::<ApiOnly>(None, None).await
+    /// Run the spa server forever with a pid file at `path`, see [pid_file](Self::pid_file)
+    pub async fn run_with_pid_file<Root>(self, root: Root, path: impl AsRef<Path>) -> Result<()>
+    where
+        Root: SpaStatic,
+    {
+        self.pid_file(path.as_ref()).run(root).await
     }
 
-    /// Run the spa server with tls and without spa root
-    #[cfg(any(feature = "openssl", feature = "rustls"))]
-    pub async fn run_api_tls(self, config: HttpsConfig) -> Result<()> {
-        self.run_raw::<ApiOnly>(None, Some(config)).await
+    /// Write pid of the process into `path` before running, and remove it after the server
+    /// stopped, for process managers like supervisord or init.d
+    ///
+    /// Running returns error if `path` contains pid of another alive process, so two instances
+    /// won't run at the same time. Stale pid file left by crashed process is overwritten.
+    pub fn pid_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.serve.pid_file = Some(path.into());
+        self
     }
 
-    /// Run the spa server with or without spa root, and with or without tls
-    async fn run_raw<Root>(mut self, root: Option<Root>, config: Option<HttpsConfig>) -> Result<()>
+    /// Run the spa server forever on a pre-bound `listener`, such as the socket passed by
+    /// systemd socket activation or inetd
+    ///
+    /// [port](Self::port), [ipv6](Self::ipv6) and [bind_reuse_port](Self::bind_reuse_port)
+    /// are ignored, the server only listens on `listener`.
+    pub async fn run_with_listener<Root>(
+        self,
+        listener: std::net::TcpListener,
+        root: Root,
+    ) -> Result<()>
     where
         Root: SpaStatic,
     {
-        if let Some(root) = root {
-            let embeded_dir = root.release(self.release_path)?;
-            let index_file = embeded_dir.clone().join("index.html");
+        self.run_raw(Some(root), None, Some(listener)).await
+    }
 
-            self.api_router = if let Some(addr) = self.forward {
-                self.api_router
-                    .fallback(forwarded_to_dev)
-                    .layer(Extension(addr))
-            } else {
-                self.api_router.fallback_service(
-                    get_service(ServeDir::new(&embeded_dir).fallback(ServeFile::new(index_file)))
-                        .layer(Self::add_cache_control())
-                        .handle_error(|e: anyhow::Error| async move {
-                            (
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                format!(
-                            "Unhandled internal server error {:?} when serve embeded path {}",
-                            e,
-                            embeded_dir.display()
-                        ),
-                            )
-                        }),
-                )
-            };
-        }
+    /// Run the spa server forever, and reload json config from `config_path` when it's changed
+    ///
+    /// `apply` is called with the [data](Self::data) once the config is loaded, so reloadable
+    /// states (such as allowed origins, credentials) should be kept in data with interior
+    /// mutability, like `Arc<RwLock<_>>`, and read by handlers or layers. The file is polled every
+    /// 2 seconds, invalid config is logged and ignored.
+    ///
+    /// Returns error if [data](Self::data) is not set, or the config can not be loaded at first.
+    #[cfg(feature = "config-reload")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "config-reload")))]
+    pub async fn run_with_config_reload<Root, Cfg, F>(
+        self,
+        root: Root,
+        config_path: impl Into<PathBuf>,
+        apply: F,
+    ) -> Result<()>
+    where
+        Root: SpaStatic,
+        Cfg: serde::de::DeserializeOwned,
+        F: Fn(&T, Cfg) + Send,
+    {
+        let data = self
+            .data
+            .clone()
+            .ok_or_else(|| anyhow!("data is required to apply reloaded config"))?;
 
-        for sf in self.static_path {
-            self.api_router = self.api_router.nest_service(
-                &sf.0,
-                get_service(ServeDir::new(&sf.1))
-                    .layer(Self::add_cache_control())
-                    .handle_error(|e: anyhow::Error| async move {
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!(
-                                "Unhandled internal server error {:?} when serve static path {}",
-                                e,
-                                sf.1.display()
-                            ),
-                        )
-                    }),
-            )
+        tokio::select! {
+            result = self.run(root) => result,
+            result = reload::watch(config_path.into(), data, apply) => result,
         }
+    }
 
-        let main_handler = |Host(hostname): Host, request: Request| async move {
-            if let Some(router) = self.host_routers.remove(&hostname) {
-                router.oneshot(request).await
-            } else {
-                self.api_router.oneshot(request).await
+    /// Run the spa server forever, and send the actual bound address through `ready_tx`
+    /// once it's listening
+    ///
+    /// It's useful with [port](Self::port) `0` in integration tests. If the server failed
+    /// before listening, `ready_tx` is dropped without sending.
+    pub async fn run_with_ready_signal<Root>(
+        self,
+        root: Root,
+        ready_tx: oneshot::Sender<SocketAddr>,
+    ) -> Result<()>
+    where
+        Root: SpaStatic,
+    {
+        let handle = self.serve.handle.clone();
+        let ready = async move {
+            if let Some(addr) = handle.listening().await {
+                let _ = ready_tx.send(addr);
+            }
+            std::future::pending().await
+        };
+
+        tokio::select! {
+            result = self.run(root) => result,
+            _ = ready => unreachable!(),
+        }
+    }
+
+    /// Setting up everything like [run](Self::run), but return the router instead of serving it,
+    /// for embedding into another axum application
+    ///
+    /// Socket options, tls and graceful shutdown settings are ignored, they belong to the outer
+    /// application. Handlers using `ConnectInfo` need the outer one to be served with
+    /// `into_make_service_with_connect_info::<SocketAddr>`.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{spa_server_root, routing::Router, SpaServer};
+    /// spa_server_root!("web/dist");
+    /// # async fn build() -> anyhow::Result<()> {
+    /// let spa = SpaServer::<()>::new()?.into_router(spa_server_root!()).await?;
+    /// let app: Router = Router::new().nest_service("/spa", spa);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn into_router<Root>(self, root: Root) -> Result<Router>
+    where
+        Root: SpaStatic,
+    {
+        Ok(self.build(Some(root), false).await?.0)
+    }
+
+    /// Run the spa server in a new thread with its own tokio runtime
+    ///
+    /// It's useful when embedding in GUI applications. This function returns after the
+    /// server starts listening, or the error if server failed to start.
+    pub fn run_in_background<Root>(self, root: Root) -> Result<BackgroundServer>
+    where
+        Root: SpaStatic + Send + 'static,
+    {
+        BackgroundServer::spawn(self, root)
+    }
+
+    /// Run the spa server with tls
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    pub async fn run_tls<Root>(self, root: Root, config: HttpsConfig) -> Result<()>
+    where
+        Root: SpaStatic,
+    {
+        self.run_raw(Some(root), Some(config), None).await
+    }
+
+    /// Run the spa server with tls on a pre-bound `listener`, see [run_with_listener](Self::run_with_listener)
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    pub async fn run_tls_with_listener<Root>(
+        self,
+        listener: std::net::TcpListener,
+        root: Root,
+        config: HttpsConfig,
+    ) -> Result<()>
+    where
+        Root: SpaStatic,
+    {
+        self.run_raw(Some(root), Some(config), Some(listener)).await
+    }
+
+    /// Run the spa server without spa root
+    pub async fn run_api(self) -> Result<()> {
+        self.run_raw::<ApiOnly>(None, None, None).await
+    }
+
+    /// Run the spa server without spa root, and redirect `GET /` to `redirect_to` with
+    /// `302 Found`, such as the API documentation
+    pub async fn run_api_with_root_redirect(self, redirect_to: &str) -> Result<()> {
+        let location = HeaderValue::from_str(redirect_to).context("invalid redirect url")?;
+        self.api_root_response(move || async move {
+            (StatusCode::FOUND, [(header::LOCATION, location)])
+        })
+        .run_api()
+        .await
+    }
+
+    /// Handle requests matching no route with `handler`, instead of `404 Not Found`
+    ///
+    /// It only applies when running without spa root, such as [run_api](Self::run_api), the
+    /// SPA fallback takes priority otherwise and a warning is logged.
+    pub fn fallback_handler<H, X>(mut self, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.routing.fallback = Some(routing::any(handler));
+        self
+    }
+
+    /// Respond `GET /` with `handler` when running without spa root, so developers who hit
+    /// the server root in browser can find the API entrypoint
+    ///
+    /// It's ignored when running with spa root, `/` is always the index page then.
+    pub fn api_root_response<H, X>(mut self, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.routing.api_root = Some(routing::get(handler));
+        self
+    }
+
+    /// Request client certificates signed by `ca_pem` in tls handshake, for mutual tls
+    ///
+    /// Clients without certificate can still connect, use [MutualTlsAuth](auth::MutualTlsAuth)
+    /// to protect routes. It only applies to [run_tls](Self::run_tls) and [run_api_tls](Self::run_api_tls).
+    #[cfg(feature = "rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn client_ca(mut self, ca_pem: impl Into<Vec<u8>>) -> Self {
+        self.serve.tls.client_ca = Some(ca_pem.into());
+        self
+    }
+
+    /// Hosting multiple domains in one port, each with its own certificate and router
+    ///
+    /// Certificate is chosen by SNI in tls handshake, the one passed to [run_tls](Self::run_tls)
+    /// or [run_api_tls](Self::run_api_tls) is used for clients without SNI or with unknown
+    /// domains. Requests are routed by host as [host_router](Self::host_router), requests of
+    /// other hosts go to the default router.
+    #[cfg(feature = "rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn sni_routing(mut self, routes: Vec<SniRoute>) -> Self {
+        for route in routes {
+            self.serve.tls.sni_certs.push((
+                route.domain.clone(),
+                HttpsConfig {
+                    certificate: route.cert_pem,
+                    private_key: route.key_pem,
+                },
+            ));
+            self.host_routers.insert(route.domain, route.router);
+        }
+        self
+    }
+
+    /// Run the spa server with tls, advertising `protocols` in ALPN negotiation
+    ///
+    /// Such as `vec![b"h2".to_vec()]` to only accept http/2 clients. [run_tls](Self::run_tls)
+    /// advertises `h2` and `http/1.1`.
+    #[cfg(feature = "rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub async fn run_tls_with_alpn<Root>(
+        mut self,
+        root: Root,
+        cert: Vec<u8>,
+        key: Vec<u8>,
+        protocols: Vec<Vec<u8>>,
+    ) -> Result<()>
+    where
+        Root: SpaStatic,
+    {
+        self.serve.tls.alpn_protocols = protocols;
+        let config = HttpsConfig {
+            certificate: cert,
+            private_key: key,
+        };
+        self.run_raw(Some(root), Some(config), None).await
+    }
+
+    /// Run the spa server with tls and without spa root
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    pub async fn run_api_tls(self, config: HttpsConfig) -> Result<()> {
+        self.run_raw::<ApiOnly>(None, Some(config), None).await
+    }
+
+    /// Run the spa server with or without spa root, and with or without tls
+    async fn run_raw<Root>(
+        mut self,
+        root: Option<Root>,
+        config: Option<HttpsConfig>,
+        listener: Option<std::net::TcpListener>,
+    ) -> Result<()>
+    where
+        Root: SpaStatic,
+    {
+        let _pid_file = self
+            .serve
+            .pid_file
+            .take()
+            .map(pid_file::PidFile::create)
+            .transpose()?;
+
+        #[allow(unused_mut)]
+        let (router, mut parts) = self.build(root, config.is_some()).await?;
+        let ipv4 = SocketAddr::from((Ipv4Addr::UNSPECIFIED, parts.port));
+        let mut servers = Vec::new();
+        let mut port = parts.port;
+        let handed_over = listener.is_some();
+        if let Some(listener) = listener {
+            if parts.serve.socket_options != SocketOptions::default() {
+                warn!("socket options are ignored when running with a pre-bound listener");
+            }
+            servers.push(axum_server::from_tcp(listener));
+        } else if parts.serve.socket_options != SocketOptions::default()
+            || (parts.port == 0 && parts.serve.ipv6.is_some())
+        {
+            let listener = Self::bind_v4(ipv4, &parts.serve.socket_options)?;
+            // the IPv6 listener should use the same port assigned by OS
+            port = listener.local_addr()?.port();
+            servers.push(axum_server::from_tcp(listener));
+        } else {
+            servers.push(axum_server::bind(ipv4));
+        }
+        if let Some(ipv6) = parts.serve.ipv6.filter(|_| !handed_over) {
+            servers.push(axum_server::from_tcp(Self::bind_ipv6_only(
+                SocketAddrV6::new(ipv6, port, 0, 0),
+                &parts.serve.socket_options,
+            )?));
+        }
+
+        if parts.serve.drain_on_shutdown {
+            let handle = parts.serve.handle.clone();
+            let timeout = parts
+                .serve
+                .shutdown_timeout
+                .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+            tokio::spawn(async move {
+                shutdown_signal().await;
+                drain_connections(handle, timeout).await;
+            });
+        }
+
+        #[cfg(feature = "acme")]
+        if let Some(auto_cert) = parts.serve.auto_cert.take() {
+            let challenge_dir = parts.serve.acme_challenge_dir.unwrap_or_default();
+            let cache_dir = parts.release_path.join("acme");
+            return auto_cert
+                .serve(
+                    servers,
+                    router,
+                    parts.serve.handle,
+                    challenge_dir,
+                    cache_dir,
+                )
+                .await;
+        }
+
+        Self::serve(servers, router, config, parts).await
+    }
+
+    /// Setting up the whole router, returns it with the options needed to serve it
+    async fn build<Root>(mut self, root: Option<Root>, https: bool) -> Result<(Router, ServeParts)>
+    where
+        Root: SpaStatic,
+    {
+        for (path, mut router, auth) in self.layers.auth_routes {
+            for layer in &self.layers.after_auth {
+                router = layer(router);
+            }
+            self.api_router = self.api_router.nest(&path, auth(router));
+        }
+
+        if let (None, Some(api_root)) = (&root, self.routing.api_root.take()) {
+            self.api_router = self.api_router.route("/", api_root);
+        }
+        match (&root, self.routing.fallback.take()) {
+            (None, Some(fallback)) => self.api_router = self.api_router.fallback_service(fallback),
+            (Some(_), Some(_)) => warn!("fallback handler is ignored when running with spa root"),
+            _ => {}
+        }
+
+        let spa_version = root.as_ref().map(|r| r.version());
+        let mut spa_index = None;
+        if let Some(root) = root {
+            let embeded_dir = root.release(self.release_path.clone())?;
+            let index_file = embeded_dir.clone().join(
+                self.statics
+                    .spa_index_file
+                    .as_deref()
+                    .unwrap_or("index.html"),
+            );
+            if self.statics.compress_html && !cfg!(debug_assertions) {
+                collapse_html_whitespace::<Root>(&embeded_dir)?;
+            }
+            #[cfg(feature = "hot-reload")]
+            if let Some(ws_path) = self
+                .statics
+                .live_reload_path
+                .filter(|_| cfg!(debug_assertions))
+            {
+                live_reload::inject_script(&index_file, &ws_path)?;
+                self.api_router = live_reload::register(self.api_router, &ws_path, &embeded_dir)?;
+            }
+            let serve_dir = ServeDir::new(&embeded_dir);
+            let serve_index = ServeFile::new(&index_file);
+            #[cfg(feature = "brotli")]
+            let (serve_dir, serve_index) = match self.statics.precompressed_br {
+                Some(quality) => {
+                    precompress_brotli::<Root>(&embeded_dir, quality)?;
+                    (serve_dir.precompressed_br(), serve_index.precompressed_br())
+                }
+                None => (serve_dir, serve_index),
+            };
+            spa_index = Some(serve_index.clone());
+
+            self.api_router = if let Some(addr) = self.forward {
+                let forwarded = HandlerWithoutStateExt::into_service(forwarded_to_dev);
+                #[cfg(feature = "reverse-proxy")]
+                let forwarded = self.routing.proxy_headers.layer(forwarded);
+                self.api_router
+                    .fallback_service(forwarded)
+                    .layer(Extension(addr))
+            } else {
+                let error_handler = self.http.error_handler.clone();
+                let condition = self
+                    .routing
+                    .spa_fallback_condition
+                    .unwrap_or(default_spa_fallback_condition);
+                let index_file = tower::service_fn(move |request: Request| {
+                    let serve_index = serve_index.clone();
+                    async move {
+                        if !condition(&request) {
+                            return Ok(StatusCode::NOT_FOUND.into_response());
+                        }
+
+                        // mark the index fallback, so it can be distinguished from real files
+                        let mut response = serve_index.oneshot(request).await?.map(Body::new);
+                        response.extensions_mut().insert(middleware::SpaFallback);
+                        Ok::<_, Infallible>(response)
+                    }
+                });
+                let etags = match self.statics.etag_strategy {
+                    Some(strategy) => etag_manifest::<Root>(&embeded_dir, strategy)?,
+                    None => HashMap::new(),
+                };
+                self.api_router.fallback_service(
+                    get_service(middleware::EtagLayer::new(etags).layer(with_index_file(
+                        serve_dir.fallback(index_file),
+                        self.statics.spa_index_file,
+                    )))
+                    .layer(Self::add_cache_control())
+                    .handle_error(|e: anyhow::Error| async move {
+                        error_response(
+                            error_handler,
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!(
+                                "Unhandled internal server error {:?} when serve embeded path {}",
+                                e,
+                                embeded_dir.display()
+                            ),
+                        )
+                    }),
+                )
+            };
+        }
+
+        for (path, env_var) in std::mem::take(&mut self.statics.path_envs) {
+            let Ok(dir) = std::env::var(&env_var) else {
+                warn!("{} is not set, static path {} is skipped", env_var, path);
+                continue;
+            };
+            if !Path::new(&dir).is_dir() {
+                return Err(anyhow!(
+                    "static path {} of {}={} is not a directory",
+                    path,
+                    env_var,
+                    dir
+                ));
+            }
+            self.static_path.push(StaticPath::new(path, dir.into()));
+        }
+
+        let max_age_rules = self.statics.max_age.iter().fold(
+            middleware::CacheRuleSet::new(),
+            |rules, (ext, secs)| {
+                let policy = match secs {
+                    0 => middleware::CachePolicy::NoCache,
+                    secs => middleware::CachePolicy::MaxAge(Duration::from_secs(*secs as u64)),
+                };
+                rules.for_extension(ext, policy)
+            },
+        );
+        for sf in self.static_path {
+            let error_handler = self.http.error_handler.clone();
+            let spa_index = spa_index.clone().filter(|_| !sf.fallthrough_for.is_empty());
+            let fallthrough_for = sf.fallthrough_for;
+            let fallthrough = tower::service_fn(move |request: Request| {
+                let spa_index = spa_index.clone();
+                let mime = path_mime(request.uri().path());
+                let fallthrough = fallthrough_for
+                    .iter()
+                    .any(|m| m.essence_str() == mime.essence_str());
+                async move {
+                    match spa_index {
+                        Some(spa_index) if fallthrough => {
+                            let mut response = spa_index.oneshot(request).await?.map(Body::new);
+                            response.extensions_mut().insert(middleware::SpaFallback);
+                            Ok::<_, Infallible>(response)
+                        }
+                        _ => Ok(StatusCode::NOT_FOUND.into_response()),
+                    }
+                }
+            });
+            self.api_router = self.api_router.nest_service(
+                &sf.path,
+                get_service(
+                    middleware::CacheControlLayer::new(max_age_rules.clone().merge(sf.cache_rules))
+                        .layer(with_index_file(
+                            ServeDir::new(&sf.dir).fallback(fallthrough),
+                            sf.index_file,
+                        )),
+                )
+                .layer(Self::add_cache_control())
+                .handle_error(|e: anyhow::Error| async move {
+                    error_response(
+                        error_handler,
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!(
+                            "Unhandled internal server error {:?} when serve static path {}",
+                            e,
+                            sf.dir.display()
+                        ),
+                    )
+                }),
+            )
+        }
+
+        #[cfg(feature = "hot-reload")]
+        for (path, dir) in self.statics.hot_reload_paths {
+            let hot_reload = middleware::HotReloadLayer::watch(&dir)?;
+            self.api_router = self
+                .api_router
+                .nest_service(&path, get_service(hot_reload.layer(ServeDir::new(&dir))));
+        }
+
+        #[cfg(feature = "swagger-ui")]
+        if let Some(swagger_ui) = self.routing.swagger_ui {
+            let spec = self.routing.openapi_spec.unwrap_or_else(|| {
+                warn!(
+                    "swagger ui enabled without openapi spec, use `openapi_spec` to specific one"
+                );
+                serde_json::Value::Null
+            });
+            self.api_router = swagger_ui.register(self.api_router, spec);
+        }
+
+        if let Some(path) = &self.routing.version_path {
+            let versions: HashMap<_, _> = [
+                ("version", Some(env!("CARGO_PKG_VERSION"))),
+                ("spa_version", spa_version),
+            ]
+            .into();
+            self.api_router = self
+                .api_router
+                .route(path, routing::get(move || async move { Json(versions) }));
+        }
+
+        for (path, checks) in std::mem::take(&mut self.health.checks) {
+            self.api_router = health::register(self.api_router, &path, checks, self.health.timeout);
+        }
+
+        #[cfg(feature = "debug")]
+        if let Some(path) = &self.routing.introspection_path {
+            let mut routes = std::mem::take(&mut self.routing.routes);
+            if let Some(version_path) = &self.routing.version_path {
+                routes.push(introspection::RouteInfo {
+                    path: version_path.clone(),
+                    methods: vec!["GET"],
+                    versions: Vec::new(),
+                });
+            }
+            routes.push(introspection::RouteInfo {
+                path: path.clone(),
+                methods: vec!["GET"],
+                versions: Vec::new(),
+            });
+            self.api_router = introspection::register(self.api_router, path, routes);
+        }
+
+        #[cfg(feature = "ws-proxy")]
+        {
+            let forward = self
+                .routing
+                .ws_proxies
+                .forward_headers
+                .include(self.routing.proxy_headers.request_header_names());
+            self.api_router = self.api_router.merge(
+                self.routing
+                    .ws_proxies
+                    .router
+                    .layer(self.routing.proxy_headers.clone())
+                    .layer(Extension(forward)),
+            );
+        }
+
+        let main_handler = |Host(hostname): Host, mut request: Request| async move {
+            // routed again by inner router, the outer matched path confuses nested services
+            request.extensions_mut().remove::<extract::MatchedPath>();
+            if let Some(router) = self.host_routers.remove(&hostname) {
+                router.oneshot(request).await
+            } else {
+                self.api_router.oneshot(request).await
             }
         };
         self.main_router = Router::new()
             .route("/", any(main_handler.clone()))
             .route("/*path", any(main_handler));
 
-        if let Some(data) = self.data {
-            self.main_router = self.main_router.layer(Extension(data));
-        }
+        if let Some(data) = self.data {
+            self.main_router = self.main_router.layer(Extension(data));
+        }
+
+        for (prefix, url) in &self.routing.not_found_redirects {
+            self.main_router = self
+                .main_router
+                .layer(middleware::NotFoundRedirectLayer::new(prefix, url));
+        }
+
+        if !self.http.response_size_limits.is_empty() {
+            let layer = self.http.response_size_limits.iter().fold(
+                middleware::ResponseSizeLimitLayer::default(),
+                |layer, (path, max)| layer.for_path(path, *max),
+            );
+            self.main_router = self.main_router.layer(layer);
+        }
+
+        for layer in self.layers.before_auth {
+            self.main_router = layer(self.main_router)
+        }
+        for layer in self.extra_layer {
+            self.main_router = layer(self.main_router)
+        }
+        for factory in self.layers.factories {
+            let layer = factory().await.context("build layer error")?;
+            self.main_router = layer(self.main_router)
+        }
+
+        if let Some(name) = &self.http.request_id_header {
+            let name = HeaderName::try_from(name.as_str())
+                .with_context(|| format!("invalid request id header {:?}", name))?;
+            // wraps all layers added by user, so they can see the id
+            self.main_router = self
+                .main_router
+                .layer(PropagateRequestIdLayer::new(name.clone()))
+                .layer(SetRequestIdLayer::new(name, MakeRequestUuid));
+        }
+        if self.http.cors_from_env {
+            let cors = middleware::cors_from_env().context("invalid cors config")?;
+            self.main_router = self.main_router.layer(cors);
+        }
+        let real_ip_headers = self
+            .http
+            .real_ip_headers
+            .iter()
+            .map(|name| {
+                HeaderName::try_from(name.as_str())
+                    .with_context(|| format!("invalid real ip header {:?}", name))
+            })
+            .collect::<Result<_>>()?;
+        self.main_router = self
+            .main_router
+            .layer(middleware::RealIpLayer::new(real_ip_headers));
+
+        #[cfg(feature = "acme")]
+        if self.serve.auto_cert.is_some() && self.serve.acme_challenge_dir.is_none() {
+            self.serve.acme_challenge_dir = Some(self.release_path.join("acme-challenge"));
+        }
+
+        if let Some(dir) = &self.serve.acme_challenge_dir {
+            // served outside all layers, so it bypasses authentication
+            self.main_router = Router::new()
+                .nest_service(ACME_CHALLENGE_PATH, ServeDir::new(dir))
+                .fallback_service(self.main_router);
+        }
+
+        if !self.routing.rewrite_rules.is_empty() {
+            self.main_router = Router::new().fallback_service(
+                middleware::PathRewriteLayer::new(self.routing.rewrite_rules)
+                    .layer(self.main_router),
+            );
+        }
+
+        if let Some(prefix) = &self.routing.strip_prefix {
+            // uri must be changed before routing, so wrap the whole router
+            self.main_router = Router::new().fallback_service(
+                middleware::StripPrefixLayer::new(prefix).layer(self.main_router),
+            );
+        }
+
+        if let Some(options) = self.routing.path_normalization {
+            self.main_router = Router::new().fallback_service(
+                middleware::PathNormalizationLayer::new(options).layer(self.main_router),
+            );
+        }
+
+        if let Some(redirect) = self.routing.host_redirect {
+            self.main_router = self
+                .main_router
+                .layer(middleware::HostRedirectLayer::new(redirect, https));
+        }
+
+        if self.http.server_timing {
+            // outermost, so the whole request handling is measured
+            self.main_router = self.main_router.layer(middleware::ServerTimingLayer::new());
+        }
+
+        let parts = ServeParts {
+            port: self.port,
+            #[cfg(feature = "acme")]
+            release_path: self.release_path,
+            serve: self.serve,
+        };
+        Ok((self.main_router, parts))
+    }
+
+    /// Serve router in all listening servers, and with or without tls
+    async fn serve(
+        servers: Vec<axum_server::Server>,
+        router: Router,
+        config: Option<HttpsConfig>,
+        parts: ServeParts,
+    ) -> Result<()> {
+        let handle = parts.serve.handle;
+        let servers = servers.into_iter().map(|s| s.handle(handle.clone()));
+        let make_service = router.into_make_service_with_connect_info::<SocketAddr>();
+        if let Some(_config) = config {
+            #[cfg(all(feature = "openssl", feature = "rustls"))]
+            compile_error!("Feature openssl and Feature rustls can not be enabled together");
+
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
+            {
+                #[cfg(feature = "rustls")]
+                let acceptor = tls::PeerCertAcceptor(axum_server::tls_rustls::RustlsAcceptor::new(
+                    tls::rustls_config(
+                        _config,
+                        parts.serve.tls.client_ca,
+                        parts.serve.tls.alpn_protocols,
+                        parts.serve.tls.sni_certs,
+                    )
+                    .await?,
+                ));
+                #[cfg(feature = "openssl")]
+                let acceptor = {
+                    let temp_dir = std::env::temp_dir().join(env!("CARGO_PKG_NAME"));
+                    std::fs::create_dir_all(&temp_dir)?;
+                    let cert_file = temp_dir.join("cert.pem");
+                    let key_file = temp_dir.join("key.pem");
+                    std::fs::write(&cert_file, &_config.certificate)?;
+                    std::fs::write(&key_file, &_config.private_key)?;
+                    axum_server::tls_openssl::OpenSSLAcceptor::new(
+                        OpenSSLConfig::from_pem_file(cert_file, key_file)
+                            .context("openssl load pem file error")?,
+                    )
+                };
+
+                try_join_all(
+                    servers.map(|s| s.acceptor(acceptor.clone()).serve(make_service.clone())),
+                )
+                .await?;
+            }
+        } else if parts.serve.serve_once {
+            let acceptor = once::OnceAcceptor::new(handle.clone());
+            try_join_all(servers.map(|s| s.acceptor(acceptor.clone()).serve(make_service.clone())))
+                .await
+                .context("serve server error")?;
+        } else {
+            try_join_all(servers.map(|s| s.serve(make_service.clone())))
+                .await
+                .context("serve server error")?;
+        }
+
+        Ok(())
+    }
+
+    /// Setting up server router, see example for usage.
+    ///
+    pub fn route(mut self, path: impl AsRef<str>, router: Router) -> Self {
+        let path = self.prefixed(path.as_ref());
+        self.record_route(&path, &["ANY"]);
+        self.api_router = self.api_router.nest(&path, router);
+        self
+    }
+
+    /// Route `GET` requests of `path` to `handler`, without wrapping it into a [Router]
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::SpaServer;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?
+    ///     .get("/api/status", || async { "ok" })
+    ///     .post("/api/status", || async { "updated" });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get<H, X>(self, path: impl AsRef<str>, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.method_route(path.as_ref(), "GET", routing::get(handler))
+    }
+
+    /// Route `POST` requests of `path` to `handler`, see [get](Self::get)
+    pub fn post<H, X>(self, path: impl AsRef<str>, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.method_route(path.as_ref(), "POST", routing::post(handler))
+    }
+
+    /// Route `PUT` requests of `path` to `handler`, see [get](Self::get)
+    pub fn put<H, X>(self, path: impl AsRef<str>, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.method_route(path.as_ref(), "PUT", routing::put(handler))
+    }
+
+    /// Route `DELETE` requests of `path` to `handler`, see [get](Self::get)
+    pub fn delete<H, X>(self, path: impl AsRef<str>, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.method_route(path.as_ref(), "DELETE", routing::delete(handler))
+    }
+
+    /// Route `PATCH` requests of `path` to `handler`, see [get](Self::get)
+    pub fn patch<H, X>(self, path: impl AsRef<str>, handler: H) -> Self
+    where
+        H: Handler<X, ()>,
+        X: 'static,
+    {
+        self.method_route(path.as_ref(), "PATCH", routing::patch(handler))
+    }
+
+    /// Routes of the same path are merged, so `get` and `post` can share one path
+    fn method_route(mut self, path: &str, method: &'static str, router: MethodRouter) -> Self {
+        let path = self.prefixed(path);
+        self.record_route(&path, &[method]);
+        self.api_router = self.api_router.route(&path, router);
+        self
+    }
+
+    /// Panic when the same path is registered twice with overlapping methods, to catch
+    /// registration bugs early
+    ///
+    /// All routes registered by [SpaServer] are checked, such as [route](Self::route),
+    /// [get](Self::get), [route_with_auth](Self::route_with_auth), static file paths, websocket
+    /// routes and health endpoints. `get("/a", ..).post("/a", ..)` is not a conflict, while
+    /// [route](Self::route) takes all methods of its path. Only routes added after this call
+    /// are checked, and it's always disabled in release build.
+    pub fn route_conflict_detection(mut self, enable: bool) -> Self {
+        self.routing.route_conflict_detection = enable && cfg!(debug_assertions);
+        self
+    }
+
+    /// Register each router of `versions` at `<prefix>/v<version>`, and redirect
+    /// `<prefix>/latest/...` to the highest version with `307 Temporary Redirect`
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{SpaServer, routing::{get, Router}};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.route_versioned(
+    ///     "/api",
+    ///     [
+    ///         (1, Router::new().route("/users", get(|| async { "v1 users" }))),
+    ///         (2, Router::new().route("/users", get(|| async { "v2 users" }))),
+    ///     ]
+    ///     .into(),
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn route_versioned(mut self, prefix: &str, mut versions: HashMap<u32, Router>) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        let mut numbers: Vec<_> = versions.keys().copied().collect();
+        numbers.sort_unstable();
+        let Some(&latest) = numbers.last() else {
+            return self;
+        };
+
+        for version in &numbers {
+            if let Some(router) = versions.remove(version) {
+                self = self.route(format!("{}/v{}", prefix, version), router);
+            }
+        }
+
+        let latest_path = self.prefixed(&format!("{}/latest", prefix));
+        let target = self.prefixed(&format!("{}/v{}", prefix, latest));
+        let redirect = {
+            let latest_path = latest_path.clone();
+            move |uri: http::Uri| async move {
+                let rest = uri.path().strip_prefix(&latest_path).unwrap_or_default();
+                let mut location = format!("{}{}", target, rest);
+                if let Some(query) = uri.query() {
+                    location = format!("{}?{}", location, query);
+                }
+                (
+                    StatusCode::TEMPORARY_REDIRECT,
+                    [(header::LOCATION, location)],
+                )
+            }
+        };
+        self.api_router = self
+            .api_router
+            .route(&latest_path, any(redirect.clone()))
+            .route(&format!("{}/*rest", latest_path), any(redirect));
+        self.record_versions(&latest_path, numbers);
+        self
+    }
+
+    /// Prefix routes added after this call with `path`, such as mounting the whole API under `/v2`
+    ///
+    /// It applies to [route](Self::route), method shortcuts such as [get](Self::get),
+    /// [route_with_auth](Self::route_with_auth) and websocket routes. Routers of [host_router](Self::host_router) are not prefixed, they
+    /// define their own path space, neither are static file paths.
+    pub fn base_path(mut self, path: &str) -> Self {
+        let path = path.trim_end_matches('/');
+        self.routing.base_path = (!path.is_empty()).then(|| path.to_string());
+        self
+    }
+
+    /// `path` prefixed with [base_path](Self::base_path)
+    fn prefixed(&self, path: &str) -> String {
+        match self.routing.base_path.as_deref() {
+            Some(base) if path == "/" => base.to_string(),
+            Some(base) => format!("{}{}", base, path),
+            None => path.to_string(),
+        }
+    }
+
+    /// Setting up server router with a layer only applied to this router
+    ///
+    /// This is equivalent to `.route(path, router.layer(layer))`
+    pub fn route_with_middleware<L, NewResBody>(
+        self,
+        path: impl AsRef<str>,
+        router: Router,
+        layer: L,
+    ) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request, Response = Response<NewResBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+        NewResBody: HttpBody<Data = Bytes> + Send + 'static,
+        NewResBody::Error: Into<BoxError>,
+    {
+        self.route(path, router.layer(layer))
+    }
+
+    /// Setting up server router which protected by an [AsyncPredicate](filter::AsyncPredicate),
+    /// such as [AsyncBasicAuth](auth::AsyncBasicAuth)
+    ///
+    /// This is equivalent to `.route(path, router.layer(AsyncFilterExLayer::new(predicate)))`,
+    /// except layers added by [layer_after_auth](Self::layer_after_auth) are applied inside the
+    /// predicate when running.
+    pub fn route_with_auth<P>(mut self, path: impl AsRef<str>, router: Router, predicate: P) -> Self
+    where
+        P: filter::AsyncPredicate<Request, Request = Request, Response = Response>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        P::Future: Send + 'static,
+    {
+        let path = self.prefixed(path.as_ref());
+        self.record_route(&path, &["ANY"]);
+        self.layers.auth_routes.push((
+            path,
+            router,
+            Box::new(move |router| router.layer(filter::AsyncFilterExLayer::new(predicate))),
+        ));
+        self
+    }
+
+    /// Reject `POST`, `PUT` and `PATCH` requests under `path` whose `Content-Type` is not
+    /// in `allowed`, with `415 Unsupported Media Type`
+    ///
+    /// See [ContentTypeFilter](filter::ContentTypeFilter) for more detail.
+    #[allow(clippy::result_large_err)]
+    pub fn content_type_filter(self, path: &str, allowed: Vec<mime::Mime>) -> Self {
+        let prefix = self.prefixed(path).trim_end_matches('/').to_string();
+        let mut filter = filter::ContentTypeFilter::new(allowed);
+        self.layer(filter::FilterExLayer::new(move |request: Request| {
+            if middleware::under_prefix(request.uri().path(), &prefix) {
+                filter::Predicate::check(&mut filter, request)
+            } else {
+                Ok(request)
+            }
+        }))
+    }
+
+    /// Serve versions in json at `path`, such as `/api/__spa_version`
+    ///
+    /// Response is `{"version": "<spa-rs version>", "spa_version": "<SpaStatic::version>"}`,
+    /// `spa_version` is `null` when running without spa root.
+    pub fn expose_version_endpoint(mut self, path: &str) -> Self {
+        self.routing.version_path = Some(path.to_string());
+        self
+    }
+
+    /// Expose a GET endpoint at `path` (usually `/__routes`) listing all registered routes as json,
+    /// such as `[{"path": "/api", "methods": ["ANY"]}]`
+    ///
+    /// Routes inside the router passed to [route](Self::route) can not be listed one by one,
+    /// only the nest path is reported with `ANY` method.
+    #[cfg(feature = "debug")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "debug")))]
+    pub fn route_introspection(mut self, path: &str) -> Self {
+        self.routing.introspection_path = Some(path.to_string());
+        self
+    }
+
+    fn record_route(&mut self, path: &str, methods: &[&'static str]) {
+        self.record_route_info(introspection::RouteInfo {
+            path: path.to_string(),
+            methods: methods.to_vec(),
+            versions: Vec::new(),
+        });
+    }
+
+    fn record_versions(&mut self, path: &str, versions: Vec<u32>) {
+        self.record_route_info(introspection::RouteInfo {
+            path: path.to_string(),
+            methods: vec!["ANY"],
+            versions,
+        });
+    }
+
+    /// the only place routes are recorded, for both conflict detection and introspection
+    fn record_route_info(&mut self, route: introspection::RouteInfo) {
+        if self.routing.route_conflict_detection
+            && self
+                .routing
+                .routes
+                .iter()
+                .any(|r| r.conflicts(&route.path, &route.methods))
+        {
+            panic!("route {} is registered more than once", route.path);
+        }
+        self.routing.routes.push(route);
+    }
+
+    /// Specific how request paths are normalized before routing, see [PathNormalizationLayer](middleware::PathNormalizationLayer)
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{SpaServer, middleware::PathNormalizationOptions};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.path_normalization(PathNormalizationOptions {
+    ///     strip_trailing_slash: true,
+    ///     collapse_slashes: true,
+    ///     ..Default::default()
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn path_normalization(mut self, options: middleware::PathNormalizationOptions) -> Self {
+        self.routing.path_normalization = Some(options);
+        self
+    }
+
+    /// Strip `prefix` from all request paths before routing, for deploying under a sub-path
+    /// behind a reverse proxy which does not strip it
+    ///
+    /// Unlike [nest](axum::Router::nest), it affects all routes, and the original path can be
+    /// accessed by [OriginalPath](middleware::OriginalPath) extension.
+    pub fn route_prefix_strip(mut self, prefix: &str) -> Self {
+        self.routing.strip_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Rewrite request paths matching regex `pattern` to `replacement` before routing,
+    /// `$1`, `$name` in `replacement` refer to capture groups
+    ///
+    /// Rules are tried in the order they are added, the first matched one wins. Path is rewritten
+    /// after [route_prefix_strip](Self::route_prefix_strip).
+    ///
+    /// # Panics
+    /// Panics if `pattern` is not a valid regex
+    pub fn rewrite_path(mut self, pattern: &str, replacement: &str) -> Self {
+        self.routing
+            .rewrite_rules
+            .push(middleware::RewriteRule::new(pattern, replacement));
+        self
+    }
+
+    /// Set `ETag` of embedded SPA files, and respond `304 Not Modified` to conditional requests
+    ///
+    /// [ContentHash](middleware::EtagStrategy::ContentHash) is computed when compiling, so it
+    /// is stable across restarts, [LastModified](middleware::EtagStrategy::LastModified) changes
+    /// every time files are released. Files served by [static_path](Self::static_path) are not affected.
+    pub fn static_file_etag_strategy(mut self, strategy: middleware::EtagStrategy) -> Self {
+        self.statics.etag_strategy = Some(strategy);
+        self
+    }
+
+    /// Read CORS config from environment variables when server starts
+    ///
+    /// - `SPA_CORS_ORIGINS`: allowed origins, default is none
+    /// - `SPA_CORS_METHODS`: allowed methods, default is `GET,HEAD`
+    /// - `SPA_CORS_HEADERS`: allowed request headers, default is none
+    ///
+    /// Each one is comma separated, or `*` to allow any. `run` returns error if any value
+    /// is invalid. The layer wraps all layers added by [layer](Self::layer), so its preflight
+    /// response takes priority over the CORS layer configured in code.
+    pub fn cors_from_env(mut self) -> Self {
+        self.http.cors_from_env = true;
+        self
+    }
+
+    /// Tunnel methods through others for clients which only support `GET` and `POST`
+    ///
+    /// `mapping` maps the overridden method to the method request must arrive with, the real
+    /// method is read from `X-HTTP-Method-Override` header or `_method` query param before
+    /// routing, see [MethodOverrideLayer](middleware::MethodOverrideLayer).
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{SpaServer, http::Method};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?
+    ///     .redirect_http_methods([(Method::DELETE, Method::POST)].into_iter().collect());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn redirect_http_methods(self, mapping: HashMap<Method, Method>) -> Self {
+        self.layer(middleware::MethodOverrideLayer::new(mapping))
+    }
+
+    /// Allow `PUT`, `PATCH` and `DELETE` to be tunneled through `POST`, with the real method
+    /// in `header` or `_method` query param
+    ///
+    /// # Panics
+    /// Panics if `header` is not a valid header name
+    pub fn enable_method_override(self, header: &str) -> Self {
+        let header = HeaderName::try_from(header).expect("invalid method override header name");
+        self.layer(middleware::MethodOverrideLayer::post_tunneling().header(header))
+    }
+
+    /// Only allow state-changing requests (`POST`, `PUT`, `DELETE`, `PATCH`) from `origins`
+    ///
+    /// A lighter alternative to full CORS config, see [OriginWhitelistLayer](middleware::OriginWhitelistLayer)
+    /// for details. Requests without `Origin` header are not checked.
+    pub fn origin_whitelist(self, origins: Vec<String>) -> Self {
+        self.layer(middleware::OriginWhitelistLayer::new(origins))
+    }
+
+    /// Specific when the SPA `index.html` fallback applies, other not found requests get `404 Not Found`
+    ///
+    /// Default is [default_spa_fallback_condition], which only falls back for browser page
+    /// requests, so API clients get real 404.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::SpaServer;
+    /// # fn main() -> anyhow::Result<()> {
+    /// // fallback for all requests
+    /// let srv = SpaServer::<()>::new()?.spa_fallback_condition(|_| true);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spa_fallback_condition(mut self, condition: fn(&Request) -> bool) -> Self {
+        self.routing.spa_fallback_condition = Some(condition);
+        self
+    }
+
+    /// Redirect not found requests under `prefix` to `url`, such as the API documentation
+    ///
+    /// SPA fallback still applies for requests not under `prefix`.
+    /// See [NotFoundRedirectLayer](middleware::NotFoundRedirectLayer) for more detail.
+    pub fn not_found_redirect_for(mut self, prefix: &str, url: &str) -> Self {
+        self.routing
+            .not_found_redirects
+            .push((prefix.to_string(), url.to_string()));
+        self
+    }
+
+    /// Authenticate all requests by a token in query parameter `param`
+    ///
+    /// It applies to all routes including SPA files, use [route_with_middleware](Self::route_with_middleware)
+    /// with [QueryParamAuth](auth::QueryParamAuth) to limit it to some routes.
+    pub fn query_param_auth<F>(self, param: &str, validator: F) -> Self
+    where
+        F: Fn(&str) -> bool + Clone + Send + Sync + 'static,
+    {
+        self.layer(filter::FilterExLayer::new(auth::QueryParamAuth::new(
+            param, validator,
+        )))
+    }
+
+    /// Setting up a websocket route in `path`
+    ///
+    /// `handler` is called with the upgraded [WebSocket](extract::ws::WebSocket).
+    ///
+    /// Subprotocol negotiation via `Sec-WebSocket-Protocol` header is not handled here,
+    /// if you need it, use [WebSocketUpgrade::protocols](extract::ws::WebSocketUpgrade::protocols)
+    /// in your own handler and setting up by [route](Self::route).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use spa_rs::SpaServer;
+    /// # use spa_rs::extract::ws::{Message, WebSocket};
+    /// #
+    /// fn echo(mut socket: WebSocket) -> spa_rs::BoxFuture<'static, ()> {
+    ///     Box::pin(async move {
+    ///         while let Some(Ok(msg)) = socket.recv().await {
+    ///             if socket.send(msg).await.is_err() {
+    ///                 break;
+    ///             }
+    ///         }
+    ///     })
+    /// }
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.websocket("/ws", echo);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn websocket(mut self, path: impl AsRef<str>, handler: WsHandler) -> Self {
+        let path = self.prefixed(path.as_ref());
+        self.record_route(&path, &["GET"]);
+        self.api_router = self.api_router.route(
+            &path,
+            routing::get(
+                move |ws: extract::WebSocketUpgrade| async move { ws.on_upgrade(handler) },
+            ),
+        );
+        self
+    }
+
+    /// Setting up a websocket route in `path`, which handler can access server context data
+    ///
+    /// `state_fn` is called with the data specificed by [data](Self::data) for every
+    /// connection, and returns the handler of upgraded [WebSocket](extract::ws::WebSocket).
+    pub fn websocket_with_state<F, H, Fut>(mut self, path: impl AsRef<str>, state_fn: F) -> Self
+    where
+        F: Fn(T) -> H + Clone + Send + Sync + 'static,
+        H: FnOnce(extract::ws::WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let path = self.prefixed(path.as_ref());
+        self.record_route(&path, &["GET"]);
+        self.api_router = self.api_router.route(
+            &path,
+            routing::get(
+                move |Extension(data): Extension<T>, ws: extract::WebSocketUpgrade| async move {
+                    ws.on_upgrade(state_fn(data))
+                },
+            ),
+        );
+        self
+    }
+
+    /// Proxy websocket connections at `from` to upstream websocket server `to`
+    ///
+    /// Path parameters in `from` can be used in `to` by `{name}`, they are percent-encoded as
+    /// one path segment, `.` and `..` are rejected with `400 Bad Request`. Query string is appended
+    /// if `to` has none. Text, binary and close frames are forwarded in both directions,
+    /// ping and pong are handled by each side itself. Client request headers are not sent to
+    /// upstream, unless specified by [forward_headers](Self::forward_headers).
+    ///
+    /// ```no_run
+    /// # use spa_rs::SpaServer;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.proxy_websocket("/ws/:id", "ws://upstream/{id}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "ws-proxy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ws-proxy")))]
+    pub fn proxy_websocket(mut self, from: &str, to: &str) -> Self {
+        let from = self.prefixed(from);
+        self.record_route(&from, &["GET"]);
+        let to = to.to_string();
+        self.routing.ws_proxies.router = self.routing.ws_proxies.router.route(
+            &from,
+            routing::get(
+                move |params: Option<extract::Path<HashMap<String, String>>>,
+                      uri: http::Uri,
+                      headers: HeaderMap,
+                      Extension(forward): Extension<ws_proxy::ForwardHeaders>,
+                      ws: extract::WebSocketUpgrade| async move {
+                    let params = params.map(|p| p.0).unwrap_or_default();
+                    let Some(url) = ws_proxy::upstream_url(&to, &params, uri.query()) else {
+                        return (StatusCode::BAD_REQUEST, "invalid path parameter").into_response();
+                    };
+                    let headers = forward.select(&headers);
+                    ws.on_upgrade(move |socket| ws_proxy::proxy(socket, url, headers))
+                },
+            ),
+        );
+        self
+    }
+
+    /// Forward `headers` of client requests verbatim to upstream in [proxy_websocket](Self::proxy_websocket),
+    /// such as `Accept-Language` or `Cookie`
+    ///
+    /// Headers of connection and websocket handshake are never forwarded.
+    #[cfg(feature = "ws-proxy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ws-proxy")))]
+    pub fn forward_headers(mut self, headers: Vec<HeaderName>) -> Self {
+        self.routing.ws_proxies.forward_headers = ws_proxy::ForwardHeaders::Only(headers.into());
+        self
+    }
 
-        for layer in self.extra_layer {
-            self.main_router = layer(self.main_router)
-        }
+    /// Same as [forward_headers](Self::forward_headers), but forward all headers except `strip`,
+    /// useful for internal upstreams
+    #[cfg(feature = "ws-proxy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ws-proxy")))]
+    pub fn forward_all_headers(mut self, strip: Vec<HeaderName>) -> Self {
+        self.routing.ws_proxies.forward_headers = ws_proxy::ForwardHeaders::AllExcept(strip.into());
+        self
+    }
 
-        let addr = format!("0.0.0.0:{}", self.port).parse()?;
-        if let Some(_config) = config {
-            #[cfg(all(feature = "openssl", feature = "rustls"))]
-            compile_error!("Feature openssl and Feature rustls can not be enabled together");
+    /// Also listening on an IPv6 address, such as [Ipv6Addr::UNSPECIFIED] for `[::]`
+    ///
+    /// The IPv6 listener use the same port, router and tls config as the IPv4 one.
+    pub fn bind_ipv6(mut self, addr: Ipv6Addr) -> Self {
+        self.serve.ipv6 = Some(addr);
+        self
+    }
 
-            #[cfg(any(feature = "openssl", feature = "rustls"))]
-            {
-                #[cfg(feature = "rustls")]
-                {
-                    axum_server::bind_rustls(
-                        addr,
-                        RustlsConfig::from_pem(_config.certificate, _config.private_key).await?,
-                    )
-                }
-                #[cfg(feature = "openssl")]
-                {
-                    let temp_dir = std::env::temp_dir().join(env!("CARGO_PKG_NAME"));
-                    std::fs::create_dir_all(&temp_dir)?;
-                    let cert_file = temp_dir.join("cert.pem");
-                    let key_file = temp_dir.join("key.pem");
-                    std::fs::write(&cert_file, &_config.certificate)?;
-                    std::fs::write(&key_file, &_config.private_key)?;
-                    axum_server::bind_openssl(
-                        addr,
-                        OpenSSLConfig::from_pem_file(cert_file, key_file)
-                            .context("openssl load pem file error")?,
-                    )
-                }
-            }
-            .serve(
-                self.main_router
-                    .into_make_service_with_connect_info::<SocketAddr>(),
-            )
-            .await?;
-        } else {
-            axum_server::bind(addr)
-                .serve(
-                    self.main_router
-                        .into_make_service_with_connect_info::<SocketAddr>(),
-                )
-                .await
-                .context("serve server error")?;
-        }
+    /// Set `SO_REUSEPORT` on listening sockets, so multiple processes can listen on the same
+    /// port and the kernel balances connections between them
+    ///
+    /// It's linux only, a warning is logged and it's ignored on other platforms.
+    pub fn bind_reuse_port(mut self, reuse: bool) -> Self {
+        self.serve.socket_options.reuse_port = reuse;
+        self
+    }
 
-        Ok(())
+    /// Set `SO_KEEPALIVE` on listening sockets, so idle connections silently dropped by load
+    /// balancers are detected
+    ///
+    /// Probes are sent after `interval` idle, and every `interval` after that, the connection
+    /// is closed after `retries` probes unanswered (`retries` is ignored on windows). Sockets are
+    /// bound by socket2 instead of `axum_server::bind` then, and accepted connections inherit
+    /// the options.
+    pub fn tcp_keepalive(mut self, interval: Duration, retries: u32) -> Self {
+        self.serve.socket_options.keepalive = Some((interval, retries));
+        self
     }
 
-    /// Setting up server router, see example for usage.
+    /// Tune the listening sockets for high throughput, sockets are bound by socket2 instead
+    /// of `axum_server::bind` then
     ///
-    pub fn route(mut self, path: impl AsRef<str>, router: Router) -> Self {
-        self.api_router = self.api_router.nest(path.as_ref(), router);
+    /// - `backlog` is the queue length of pending connections, default is 1024 when bound by
+    ///   socket2. Larger values (such as 4096) absorb connection bursts, but the OS caps it
+    ///   (`net.core.somaxconn` on linux).
+    /// - `recv_buffer_size` and `send_buffer_size` set `SO_RCVBUF` and `SO_SNDBUF`, `None` keeps
+    ///   the OS default, which is usually auto tuned. Larger buffers (such as 1MB) improve
+    ///   throughput on high latency links, at the cost of memory of every connection.
+    ///
+    /// They are ignored with [run_with_listener](Self::run_with_listener), as the socket is
+    /// bound already.
+    pub fn bind_tcp_options(
+        mut self,
+        backlog: u32,
+        recv_buffer_size: Option<u32>,
+        send_buffer_size: Option<u32>,
+    ) -> Self {
+        self.serve.socket_options.backlog = Some(backlog);
+        self.serve.socket_options.recv_buffer_size = recv_buffer_size;
+        self.serve.socket_options.send_buffer_size = send_buffer_size;
         self
     }
 
     /// Server listening port, default is 8080
     ///
+    /// `0` lets OS assign a free port, which is useful for parallel tests, and the IPv6
+    /// listener uses the same one. It works with tls too. The actual port can be got by
+    /// [run_with_ready_signal](Self::run_with_ready_signal) or [BackgroundServer::port].
     pub fn port(mut self, port: u16) -> Self {
         self.port = port;
         self
@@ -384,7 +2315,135 @@ where
     ///
     /// Unlike [spa_server_root], file in this path can be changed in runtime.
     pub fn static_path(mut self, path: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
-        self.static_path.push((path.into(), dir.into()));
+        let path = path.into();
+        self.record_route(&path, &["GET", "HEAD"]);
+        self.static_path.push(StaticPath::new(path, dir.into()));
+        self
+    }
+
+    /// Same as [static_path](Self::static_path), but requests of missing files whose mime type
+    /// is in `fallthrough_for` fall back to the SPA `index.html` instead of `404 Not Found`
+    ///
+    /// Mime type is detected from the extension of request path, path without extension is
+    /// detected as `text/html`. So with `vec![mime::TEXT_HTML]`, `/dashboard/settings` gets
+    /// the SPA while `/images/missing.png` is still 404.
+    pub fn static_path_with_fallthrough(
+        mut self,
+        path: impl Into<String>,
+        dir: impl Into<PathBuf>,
+        fallthrough_for: Vec<mime::Mime>,
+    ) -> Self {
+        let path = path.into();
+        self.record_route(&path, &["GET", "HEAD"]);
+        self.static_path.push(StaticPath {
+            fallthrough_for,
+            ..StaticPath::new(path, dir.into())
+        });
+        self
+    }
+
+    /// Same as [static_path](Self::static_path), but `Cache-Control` is set by the extension
+    /// of request path with `rules`, such as [CacheRuleSet::default_spa_rules](middleware::CacheRuleSet::default_spa_rules)
+    ///
+    /// Files whose extension matches no rule are `max-age=300` as usual.
+    pub fn static_path_with_cache_rules(
+        mut self,
+        path: impl Into<String>,
+        dir: impl Into<PathBuf>,
+        rules: middleware::CacheRuleSet,
+    ) -> Self {
+        let path = path.into();
+        self.record_route(&path, &["GET", "HEAD"]);
+        self.static_path.push(StaticPath {
+            cache_rules: rules,
+            ..StaticPath::new(path, dir.into())
+        });
+        self
+    }
+
+    /// Same as [static_path](Self::static_path), but directory requests (paths ending with `/`)
+    /// are served with `index_file`, such as `index.htm` or `default.html`, instead of `index.html`
+    pub fn static_path_with_index(
+        mut self,
+        path: impl Into<String>,
+        dir: impl Into<PathBuf>,
+        index_file: &str,
+    ) -> Self {
+        let path = path.into();
+        self.record_route(&path, &["GET", "HEAD"]);
+        self.static_path.push(StaticPath {
+            index_file: Some(index_file.to_string()),
+            ..StaticPath::new(path, dir.into())
+        });
+        self
+    }
+
+    /// Use `index_file` of the embedded SPA, such as `app.html`, instead of `index.html`
+    ///
+    /// It's served for directory requests and as the SPA fallback.
+    pub fn spa_index_file(mut self, index_file: &str) -> Self {
+        self.statics.spa_index_file = Some(index_file.to_string());
+        self
+    }
+
+    /// Same as [static_path](Self::static_path), but the directory is read from environment
+    /// variable `env_var` when server starts, such as `STATIC_DIR=/srv/assets`
+    ///
+    /// The static path is skipped with a warning if `env_var` is not set, and `run` returns
+    /// error if it is not a directory.
+    pub fn static_path_from_env(mut self, route_path: impl Into<String>, env_var: &str) -> Self {
+        let path = route_path.into();
+        self.record_route(&path, &["GET", "HEAD"]);
+        self.statics.path_envs.push((path, env_var.to_string()));
+        self
+    }
+
+    /// Set `Cache-Control` of files with `ext` in all static paths to `max-age=<seconds>`
+    ///
+    /// `0` means `no-cache`, extensions not configured are `max-age=300` as usual. Rules of
+    /// [static_path_with_cache_rules](Self::static_path_with_cache_rules) take priority.
+    pub fn static_path_max_age(mut self, ext: &str, seconds: u32) -> Self {
+        self.statics
+            .max_age
+            .insert(ext.trim_start_matches('.').to_ascii_lowercase(), seconds);
+        self
+    }
+
+    /// Same as [static_path](Self::static_path), but watch changes of files in `dir`
+    ///
+    /// Responses are revalidated by clients every time with `ETag`, which changes when the
+    /// file is changed, so clients get the latest file without waiting for cache expiring.
+    #[cfg(feature = "hot-reload")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reload")))]
+    pub fn hot_reload_static_path(
+        mut self,
+        path: impl Into<String>,
+        dir: impl Into<PathBuf>,
+    ) -> Self {
+        let path = path.into();
+        self.record_route(&path, &["GET", "HEAD"]);
+        self.statics.hot_reload_paths.push((path, dir.into()));
+        self
+    }
+
+    /// Collapse consecutive whitespace of released html files, except in `<pre>`, `<script>`,
+    /// `<style>` and `<textarea>`
+    ///
+    /// Default is `false`, and it's always disabled in debug builds.
+    pub fn compress_embedded_html(mut self, compress: bool) -> Self {
+        self.statics.compress_html = compress;
+        self
+    }
+
+    /// Reload pages in browser when released files changed, without a separate dev server
+    ///
+    /// A script connecting to the websocket at `ws_path` is injected into the released
+    /// `index.html`, and pages reload when any file in [release_path](Self::release_path)
+    /// changed, or the server restarted. It's only active in debug builds.
+    #[cfg(feature = "hot-reload")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reload")))]
+    pub fn live_reload_script(mut self, ws_path: &str) -> Self {
+        self.statics.live_reload_path = Some(ws_path.to_string());
         self
     }
 
@@ -395,6 +2454,228 @@ where
         self
     }
 
+    /// Specific the max time waiting for in-flight requests when shutdown gracefully,
+    /// connections still alive after that will be dropped.
+    ///
+    /// It applies to [BackgroundServer::stop] and
+    /// [drain_connections_on_shutdown](Self::drain_connections_on_shutdown).
+    /// Default is [DEFAULT_SHUTDOWN_TIMEOUT]
+    pub fn graceful_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.serve.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Respond `GET path` with health of all dependencies, `checks` are running concurrently
+    ///
+    /// Responds `200 OK` if all checks passed, `207 Multi-Status` if some failed, and
+    /// `503 Service Unavailable` if all failed, with a body like
+    /// `{"status": "degraded", "dependencies": {"db": true, "cache": false}}`.
+    /// Checks not finished in [health_check_timeout](Self::health_check_timeout) are failed.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{HealthCheck, SpaServer};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.health_check_with_dependencies(
+    ///     "/health",
+    ///     vec![HealthCheck {
+    ///         name: "db".to_string(),
+    ///         check: Box::new(|| Box::pin(async { true })),
+    ///     }],
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn health_check_with_dependencies(mut self, path: &str, checks: Vec<HealthCheck>) -> Self {
+        let path = self.prefixed(path);
+        self.record_route(&path, &["GET"]);
+        self.health.checks.push((path, checks));
+        self
+    }
+
+    /// Specific the max time waiting for each check of
+    /// [health_check_with_dependencies](Self::health_check_with_dependencies)
+    ///
+    /// Default is [DEFAULT_HEALTH_CHECK_TIMEOUT]
+    pub fn health_check_timeout(mut self, timeout: Duration) -> Self {
+        self.health.timeout = timeout;
+        self
+    }
+
+    /// Shutdown gracefully when receiving `Ctrl-C` or `SIGTERM`, waiting at most
+    /// [graceful_shutdown_timeout](Self::graceful_shutdown_timeout) for in-flight requests
+    ///
+    /// Connections still alive after that are closed forcibly, the numbers of draining and
+    /// dropped connections are logged.
+    pub fn drain_connections_on_shutdown(mut self) -> Self {
+        self.serve.drain_on_shutdown = true;
+        self
+    }
+
+    /// Trust the header set by reverse proxy to read [RealIp](middleware::RealIp) from,
+    /// such as `X-Real-IP` for nginx
+    ///
+    /// No header is trusted by default, [RealIp](middleware::RealIp) is the peer address then.
+    /// Only call it behind a reverse proxy which overwrites the header, otherwise any client
+    /// can forge it. Invalid header names are reported as error when the server starts.
+    pub fn x_real_ip_header(self, header: &str) -> Self {
+        self.x_real_ip_headers([header])
+    }
+
+    /// Same as [x_real_ip_header](Self::x_real_ip_header), but tries multiple headers in order
+    pub fn x_real_ip_headers<'a>(mut self, headers: impl IntoIterator<Item = &'a str>) -> Self {
+        self.http.real_ip_headers = headers.into_iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Sign session cookie values with `secret`, see [CookieSecret](session::CookieSecret)
+    ///
+    /// The secret can be accessed by Extension in all handlers, and is used by
+    /// [RequireSession](session::RequireSession) which has no secret of its own. Prefer
+    /// [RequireSession::secret](session::RequireSession::secret) so the check doesn't depend
+    /// on this layer wrapping it.
+    pub fn cookie_secret(self, secret: &[u8]) -> Self {
+        self.layer(Extension(session::CookieSecret::new(secret)))
+    }
+
+    /// Same as [cookie_secret](Self::cookie_secret), but with legacy secrets for rotation,
+    /// the first one is the current secret
+    ///
+    /// # Panics
+    /// Panics if `secrets` is empty
+    pub fn cookie_secrets(self, secrets: Vec<[u8; 32]>) -> Self {
+        self.layer(Extension(session::CookieSecret::with_rotation(secrets)))
+    }
+
+    /// Specific many extensions at once, each value in `extensions` can be accessed by
+    /// [Extension] with its own type in all handlers
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{SpaServer, TypeMap};
+    /// # #[derive(Clone)] struct AppConfig;
+    /// # #[derive(Clone)] struct Mailer;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut extensions = TypeMap::new();
+    /// extensions.insert(AppConfig).insert(Mailer);
+    /// let srv = SpaServer::<()>::new()?.global_extensions(extensions);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn global_extensions(mut self, extensions: TypeMap) -> Self {
+        self.extra_layer.push(Box::new(move |app| {
+            app.layer(axum::middleware::map_request(
+                move |mut request: Request| {
+                    extensions.apply(request.extensions_mut());
+                    async move { request }
+                },
+            ))
+        }));
+        self
+    }
+
+    /// Specific a handler to build the response when serving static files failed
+    ///
+    /// Default is a plain text response with the error message, the handler can return
+    /// a branded html page or a json response instead.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use spa_rs::{SpaServer, http::StatusCode, response::{Html, IntoResponse}};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let srv = SpaServer::<()>::new()?.error_handler(|status: StatusCode, message: String| {
+    ///     (status, Html(format!("<h1>Oops</h1><p>{}</p>", message))).into_response()
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(StatusCode, String) -> Response + Send + Sync + 'static,
+    {
+        self.http.error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Serve files in `dir` at [ACME_CHALLENGE_PATH], for ACME HTTP-01 challenges
+    ///
+    /// These files are served before all other routes and layers, so they bypass
+    /// any authentication.
+    pub fn acme_challenge_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.serve.acme_challenge_dir = Some(dir.into());
+        self
+    }
+
+    /// Issue and renew certificate automatically from Let's Encrypt, and serve with tls
+    ///
+    /// HTTP-01 challenge is served by an extra listener on port 80, so the server itself
+    /// should listen on another port such as 443. Challenge files are written into
+    /// [acme_challenge_dir](Self::acme_challenge_dir) (default is under [release_path](Self::release_path)),
+    /// and account, certificate are cached under [release_path](Self::release_path).
+    #[cfg(feature = "acme")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "acme")))]
+    pub fn auto_cert(mut self, domains: Vec<String>, contact_email: &str) -> Self {
+        self.serve.auto_cert = Some(acme::AutoCert::new(domains, contact_email));
+        self
+    }
+
+    /// Redirect `www.example.com` to `example.com` with `301 Moved Permanently`
+    ///
+    /// Scheme and port are preserved. Use [host_router](Self::host_router) to setting up
+    /// router for the canonical domain if needed.
+    pub fn redirect_www_to_apex(mut self) -> Self {
+        self.routing.host_redirect = Some(middleware::HostRedirect::WwwToApex);
+        self
+    }
+
+    /// Redirect `example.com` to `www.example.com` with `301 Moved Permanently`
+    ///
+    /// Scheme and port are preserved. Use [host_router](Self::host_router) to setting up
+    /// router for the canonical domain if needed.
+    pub fn redirect_apex_to_www(mut self) -> Self {
+        self.routing.host_redirect = Some(middleware::HostRedirect::ApexToWww);
+        self
+    }
+
+    /// Hosting a Swagger UI page in `path`, which loads OpenAPI spec from `spec_path`
+    ///
+    /// The spec is provided by [openapi_spec](Self::openapi_spec). Spec route is registered
+    /// at server root, so it bypasses any middleware applied to nested routers. The ui is a
+    /// minimal viewer embedded into the binary, its assets are served under `path`.
+    #[cfg(feature = "swagger-ui")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "swagger-ui")))]
+    pub fn swagger_ui(mut self, path: impl Into<String>, spec_path: impl Into<String>) -> Self {
+        let path = self.prefixed(&path.into());
+        let spec_path = self.prefixed(&spec_path.into());
+        self.record_route(&path, &["GET"]);
+        self.record_route(&spec_path, &["GET"]);
+        self.routing.swagger_ui = Some(swagger::SwaggerUi { path, spec_path });
+        self
+    }
+
+    /// OpenAPI spec served by [swagger_ui](Self::swagger_ui)
+    #[cfg(feature = "swagger-ui")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "swagger-ui")))]
+    pub fn openapi_spec(mut self, spec: serde_json::Value) -> Self {
+        self.routing.openapi_spec = Some(spec);
+        self
+    }
+
+    /// bind IPv6 only socket, so it will not conflict with the IPv4 listener
+    fn bind_ipv6_only(
+        addr: SocketAddrV6,
+        options: &SocketOptions,
+    ) -> Result<std::net::TcpListener> {
+        let socket = Socket::new(Domain::IPV6, Type::STREAM, Some(Protocol::TCP))?;
+        socket.set_only_v6(true)?;
+        options.listen(socket, SocketAddr::V6(addr))
+    }
+
+    fn bind_v4(addr: SocketAddr, options: &SocketOptions) -> Result<std::net::TcpListener> {
+        let socket = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP))?;
+        options.listen(socket, addr)
+    }
+
     fn add_cache_control() -> SetResponseHeaderLayer<HeaderValue> {
         SetResponseHeaderLayer::if_not_present(
             header::CACHE_CONTROL,
@@ -403,6 +2684,55 @@ where
     }
 }
 
+/// Resolves when `Ctrl-C` or `SIGTERM` (on unix) is received
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("listen SIGTERM error: {:?}", e);
+                std::future::pending::<()>().await
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate => {},
+    }
+}
+
+/// Shutdown `handle` gracefully, connections still alive after `timeout` are dropped
+pub(crate) async fn drain_connections(handle: Handle, timeout: Duration) {
+    let draining = handle.connection_count();
+    if draining > 0 {
+        info!("shutting down, draining {} connections", draining);
+    }
+    // force closing by ourselves instead of `Some(timeout)`, so the dropped ones can be counted
+    handle.graceful_shutdown(None);
+    let deadline = tokio::time::Instant::now() + timeout;
+    while handle.connection_count() > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let aborted = handle.connection_count();
+    if aborted > 0 {
+        warn!(
+            "graceful shutdown timeout after {:?}, {} connections dropped",
+            timeout, aborted
+        );
+        handle.shutdown();
+    }
+}
+
+/// Handler of upgraded websocket, see [SpaServer::websocket]
+pub type WsHandler = fn(extract::ws::WebSocket) -> BoxFuture<'static, ()>;
+
 pub struct HttpsConfig {
     pub certificate: Vec<u8>,
     pub private_key: Vec<u8>,
@@ -411,7 +2741,9 @@ pub struct HttpsConfig {
 /// setup https pems   
 ///
 /// ## Example
-/// ```
+/// The folder is embedded when compiling, so this example is not compiled as a doctest.
+/// ```ignore
+/// use spa_rs::https_pems;
 /// https_pems!("/some/folder/contains/two/pem/file");
 /// ```
 ///
@@ -468,16 +2800,106 @@ macro_rules! spa_server_root {
         #[folder = $root]
         struct StaticFiles;
 
-        impl spa_rs::SpaStatic for StaticFiles {}
+        impl spa_rs::SpaStatic for StaticFiles {
+            fn version(&self) -> &'static str {
+                env!("CARGO_PKG_VERSION")
+            }
+        }
     };
     () => {
         StaticFiles
     };
 }
 
+/// Write `.br` companion files of released text files in `dir`
+#[cfg(feature = "brotli")]
+fn precompress_brotli<Root: SpaStatic>(dir: &Path, quality: u32) -> Result<()> {
+    use std::io::Write;
+
+    const EXTENSIONS: &[&str] = &[
+        "html", "htm", "js", "mjs", "css", "json", "map", "svg", "txt", "xml", "wasm",
+    ];
+    for file in Root::iter() {
+        let path = dir.join(file.as_ref());
+        let compressible = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EXTENSIONS.contains(&e));
+        if !compressible {
+            continue;
+        }
+
+        let content = fs::read(&path)?;
+        let mut br_path = path.into_os_string();
+        br_path.push(".br");
+        let mut writer =
+            brotli::CompressorWriter::new(fs::File::create(br_path)?, 4096, quality, 22);
+        writer.write_all(&content)?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Collapse whitespace of released html files in `dir`
+fn collapse_html_whitespace<Root: SpaStatic>(dir: &Path) -> Result<()> {
+    for file in Root::iter() {
+        let path = dir.join(file.as_ref());
+        let is_html = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"));
+        if !is_html {
+            continue;
+        }
+
+        let html = fs::read_to_string(&path)?;
+        fs::write(&path, minify::collapse_whitespace(&html))?;
+    }
+
+    Ok(())
+}
+
+/// ETags of embedded files released in `dir`, keyed by request path
+fn etag_manifest<Root: SpaStatic>(
+    dir: &Path,
+    strategy: middleware::EtagStrategy,
+) -> Result<HashMap<String, String>> {
+    let mut etags = HashMap::new();
+    for file in Root::iter() {
+        let etag = match strategy {
+            middleware::EtagStrategy::ContentHash => match Root::get(&file) {
+                Some(f) => f
+                    .metadata
+                    .sha256_hash()
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect(),
+                None => continue,
+            },
+            middleware::EtagStrategy::LastModified => {
+                let modified = fs::metadata(dir.join(file.as_ref()))?.modified()?;
+                let millis = modified.duration_since(std::time::UNIX_EPOCH)?.as_millis();
+                format!("{:#x}", millis)
+            }
+        };
+        etags.insert(format!("/{}", file), etag);
+    }
+
+    Ok(etags)
+}
+
 /// Used to release static file into temp dir in runtime.
 ///
 pub trait SpaStatic: rust_embed::RustEmbed {
+    /// Version of embedded SPA files, see [expose_version_endpoint](SpaServer::expose_version_endpoint)
+    ///
+    /// [spa_server_root] returns the version of the crate calling it, override it to return
+    /// a build hash if needed, such as `env!("SPA_BUILD_HASH")`.
+    fn version(&self) -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
     fn release(&self, release_path: PathBuf) -> Result<PathBuf> {
         let target_dir = release_path;
         if !target_dir.exists() {
@@ -512,9 +2934,336 @@ impl rust_embed::RustEmbed for ApiOnly {
         unreachable!()
     }
 
-    fn iter() -> rust_embed::Filenames {
-        unreachable!()
+    fn iter() -> impl Iterator<Item = std::borrow::Cow<'static, str>> + 'static {
+        std::iter::empty()
     }
 }
 
 struct ApiOnly;
+
+#[cfg(test)]
+mod test {
+    use super::{ApiOnly, SpaServer, SpaStatic};
+    use axum::{
+        body::{Body, Bytes},
+        extract::Request,
+        http::{header, Method, StatusCode},
+        response::Response,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    async fn call(server: SpaServer<()>, request: Request) -> Response {
+        let (router, _) = server.build::<ApiOnly>(None, false).await.unwrap();
+        router.oneshot(request).await.unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    fn request(method: Method, uri: &str) -> Request {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::HOST, "localhost")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_response_body_transform() {
+        let server = || {
+            SpaServer::<()>::new()
+                .unwrap()
+                .get("/page", || async {
+                    (
+                        [
+                            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+                            (header::ETAG, "\"v1\""),
+                        ],
+                        "<body></body>",
+                    )
+                })
+                .get("/large", || async {
+                    ([(header::CONTENT_TYPE, "text/html")], "<body>large</body>")
+                })
+                .get("/missing", || async {
+                    (
+                        StatusCode::NOT_FOUND,
+                        [(header::CONTENT_TYPE, "text/html")],
+                        "<body></body>",
+                    )
+                })
+                .response_body_transform(mime::TEXT_HTML, 16, |body| {
+                    let html = String::from_utf8_lossy(&body)
+                        .replace("</body>", "<script></script></body>");
+                    Bytes::from(html)
+                })
+        };
+
+        let response = call(server(), request(Method::GET, "/page")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(header::ETAG));
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "30");
+        assert_eq!(
+            body_string(response).await,
+            "<body><script></script></body>"
+        );
+
+        let response = call(server(), request(Method::GET, "/large")).await;
+        assert_eq!(body_string(response).await, "<body>large</body>");
+
+        let response = call(server(), request(Method::GET, "/missing")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_string(response).await, "<body></body>");
+
+        let response = call(server(), request(Method::HEAD, "/page")).await;
+        assert_eq!(response.headers()[header::ETAG], "\"v1\"");
+    }
+
+    #[test]
+    #[should_panic(expected = "route /a is registered more than once")]
+    fn test_route_conflict() {
+        let _ = SpaServer::<()>::new()
+            .unwrap()
+            .route_conflict_detection(true)
+            .route("/a", Router::new())
+            .route("/a", Router::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "route /a is registered more than once")]
+    fn test_route_conflict_across_methods() {
+        let _ = SpaServer::<()>::new()
+            .unwrap()
+            .route_conflict_detection(true)
+            .health_check_with_dependencies("/a", Vec::new())
+            .route("/a", Router::new());
+    }
+
+    #[test]
+    fn test_route_no_conflict() {
+        let _ = SpaServer::<()>::new()
+            .unwrap()
+            .route_conflict_detection(true)
+            .get("/a", || async { "get" })
+            .post("/a", || async { "post" })
+            .route("/b", Router::new());
+    }
+
+    #[derive(rust_embed::RustEmbed)]
+    #[folder = "web/dist"]
+    struct TestRoot;
+
+    impl SpaStatic for TestRoot {}
+
+    #[tokio::test]
+    async fn test_spa_fallback() {
+        let release_path = std::env::temp_dir().join("spa-rs-test-spa-fallback");
+        let (router, _) = SpaServer::<()>::new()
+            .unwrap()
+            .release_path(&release_path)
+            .get("/api/status", || async { "ok" })
+            .build(Some(TestRoot), false)
+            .await
+            .unwrap();
+        let call = |uri: &str, accept: &str| {
+            let request = Request::builder()
+                .uri(uri)
+                .header(header::HOST, "localhost")
+                .header(header::ACCEPT, accept)
+                .body(Body::empty())
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+        let index = std::fs::read_to_string("web/dist/index.html").unwrap();
+
+        let html = "text/html,application/xhtml+xml,*/*;q=0.8";
+        let response = call("/some/page", html).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, index);
+
+        let response = call("/some/page", "application/json").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = call("/api/missing", html).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = call("/api/status", "*/*").await.unwrap();
+        assert_eq!(body_string(response).await, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_into_router() {
+        let spa = SpaServer::<()>::new()
+            .unwrap()
+            .release_path(std::env::temp_dir().join("spa-rs-test-into-router"))
+            .get("/api/status", || async { "ok" })
+            .into_router(TestRoot)
+            .await
+            .unwrap();
+        let app = Router::new().nest_service("/spa", spa);
+
+        let response = app
+            .clone()
+            .oneshot(request(Method::GET, "/spa/index.html"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let index = std::fs::read_to_string("web/dist/index.html").unwrap();
+        assert_eq!(body_string(response).await, index);
+
+        let response = app
+            .oneshot(request(Method::GET, "/spa/api/status"))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_run_once_with_pid_file() {
+        use std::io::{Read, Write};
+
+        let pid_file = std::env::temp_dir().join("spa-rs-test-run-once.pid");
+        let server = SpaServer::<()>::new()
+            .unwrap()
+            .port(0)
+            .release_path(std::env::temp_dir().join("spa-rs-test-run-once"))
+            .get("/api/status", || async { "ok" })
+            .pid_file(&pid_file);
+        let handle = server.serve.handle.clone();
+        let running = tokio::spawn(server.run_once(TestRoot));
+
+        let port = handle.listening().await.unwrap().port();
+        assert_eq!(
+            std::fs::read_to_string(&pid_file).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        let response = tokio::task::spawn_blocking(move || {
+            let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+            stream
+                .write_all(
+                    b"GET /api/status HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        })
+        .await
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("ok"));
+
+        // stops after the first connection is closed
+        tokio::time::timeout(std::time::Duration::from_secs(5), running)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(!pid_file.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pid_file_of_alive_process() {
+        let pid_file = std::env::temp_dir().join("spa-rs-test-alive.pid");
+        std::fs::write(&pid_file, "1\n").unwrap();
+        let result = SpaServer::<()>::new()
+            .unwrap()
+            .port(0)
+            .pid_file(&pid_file)
+            .run_api()
+            .await;
+        std::fs::remove_file(&pid_file).unwrap();
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("another instance is running with pid 1"));
+    }
+
+    #[tokio::test]
+    async fn test_request_body_logging_for() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let server = || {
+            let tx = tx.clone();
+            SpaServer::<()>::new()
+                .unwrap()
+                .post("/api/echo", |body: Bytes| async move { body })
+                .post("/other", |body: Bytes| async move { body })
+                .request_body_logging_for("/api", 8, move |body, request| {
+                    let _ = tx.send((request.uri().path().to_string(), body));
+                })
+        };
+        let post = |uri: &str, body: &'static str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(header::HOST, "localhost")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = call(server(), post("/api/echo", "hello")).await;
+        assert_eq!(body_string(response).await, "hello");
+        let (path, body) = rx.recv().await.unwrap();
+        assert_eq!(path, "/api/echo");
+        assert_eq!(body, "hello");
+
+        // not logged, but still handled with the whole body
+        let response = call(server(), post("/api/echo", "too large to log")).await;
+        assert_eq!(body_string(response).await, "too large to log");
+        let response = call(server(), post("/other", "hello")).await;
+        assert_eq!(body_string(response).await, "hello");
+        drop(tx);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_with_dependencies() {
+        use super::HealthCheck;
+        use std::time::Duration;
+
+        let check = |name: &str, passed: bool, delay: u64| HealthCheck {
+            name: name.to_string(),
+            check: Box::new(move || {
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    passed
+                })
+            }),
+        };
+        let server = || {
+            SpaServer::<()>::new()
+                .unwrap()
+                .health_check_timeout(Duration::from_millis(100))
+                .health_check_with_dependencies("/healthy", vec![check("db", true, 0)])
+                .health_check_with_dependencies(
+                    "/degraded",
+                    vec![check("db", true, 0), check("cache", false, 0)],
+                )
+                .health_check_with_dependencies("/unhealthy", vec![check("slow", true, 1000)])
+        };
+
+        let response = call(server(), request(Method::GET, "/healthy")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_string(response).await,
+            r#"{"status":"healthy","dependencies":{"db":true}}"#
+        );
+
+        let response = call(server(), request(Method::GET, "/degraded")).await;
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        assert_eq!(
+            body_string(response).await,
+            r#"{"status":"degraded","dependencies":{"db":true,"cache":false}}"#
+        );
+
+        let response = call(server(), request(Method::GET, "/unhealthy")).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            body_string(response).await,
+            r#"{"status":"unhealthy","dependencies":{"slow":false}}"#
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Reloading pages in browser when released files changed, for development without
+//! a separate dev server.
+//!
+//! A script connecting to the websocket at `ws_path` is injected into `index.html`, the
+//! page is reloaded when `reload` is received, or when the connection is re-established
+//! after the server restarted.
+use anyhow::{Context, Result};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        WebSocketUpgrade,
+    },
+    routing::get,
+    Router,
+};
+use log::warn;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{fs, path::Path, sync::Arc};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+(() => {
+    const connect = (reconnecting) => {
+        const scheme = location.protocol === "https:" ? "wss" : "ws";
+        const ws = new WebSocket(`${scheme}://${location.host}{{ws_path}}`);
+        ws.onopen = () => reconnecting && location.reload();
+        ws.onmessage = (e) => e.data === "reload" && location.reload();
+        ws.onclose = () => setTimeout(() => connect(true), 1000);
+    };
+    connect(false);
+})();
+</script>
+"#;
+
+/// Insert the live reload script before `</body>` of `index`
+pub(crate) fn inject_script(index: &Path, ws_path: &str) -> Result<()> {
+    let html = fs::read_to_string(index)
+        .with_context(|| format!("read index file {} error", index.display()))?;
+    let script = LIVE_RELOAD_SCRIPT.replace("{{ws_path}}", ws_path);
+    let html = match html.rfind("</body>") {
+        Some(pos) => format!("{}{}{}", &html[..pos], script, &html[pos..]),
+        None => html + &script,
+    };
+    fs::write(index, html).with_context(|| format!("write index file {} error", index.display()))
+}
+
+/// Register the websocket at `ws_path` into `router`, which sends `reload` when any file
+/// in `dir` changed
+pub(crate) fn register(router: Router, ws_path: &str, dir: &Path) -> Result<Router> {
+    let (tx, _) = broadcast::channel(16);
+    let changed = tx.clone();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                let _ = changed.send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("watch released files error: {}", e),
+        })?;
+    watcher.watch(dir, RecursiveMode::Recursive)?;
+    let watcher = Arc::new(watcher);
+
+    Ok(router.route(
+        ws_path,
+        get(move |ws: WebSocketUpgrade| {
+            // keep the watcher alive as long as the route
+            let _watcher = watcher.clone();
+            let mut rx = tx.subscribe();
+            async move {
+                ws.on_upgrade(move |mut socket: WebSocket| async move {
+                    while let Ok(()) | Err(RecvError::Lagged(_)) = rx.recv().await {
+                        if socket.send(Message::Text("reload".into())).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+            }
+        }),
+    ))
+}
@@ -0,0 +1,259 @@
+use super::{try_buffer_body, Buffered, RealIp};
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    mem,
+    net::IpAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+use tower::{Layer, Service};
+use tower_http::request_id::RequestId;
+
+/// A request captured by [RequestBodySnifferLayer]
+#[derive(Clone, Debug)]
+pub struct AuditRecord {
+    pub timestamp: SystemTime,
+    pub method: Method,
+    pub uri: Uri,
+    /// request body, truncated to `max_body_bytes` of [RequestBodySnifferLayer]
+    pub body: Bytes,
+    /// whether `body` is only a part of the request body
+    pub truncated: bool,
+    /// [RealIp] of the request, the peer address if no real ip header is trusted
+    pub client_ip: Option<IpAddr>,
+    /// [RequestId] of the request, set by [SpaServer::request_id](crate::SpaServer::request_id)
+    /// or [SetRequestIdLayer](tower_http::request_id::SetRequestIdLayer)
+    pub request_id: Option<String>,
+}
+
+/// Bounded storage of [AuditRecord], the oldest record is dropped when full
+#[derive(Debug)]
+pub struct AuditStore {
+    records: Mutex<VecDeque<AuditRecord>>,
+    max_records: usize,
+}
+
+impl AuditStore {
+    /// returns a new store holds at most `max_records` records
+    pub fn new(max_records: usize) -> Self {
+        Self {
+            records: Mutex::new(VecDeque::with_capacity(max_records.min(1024))),
+            max_records,
+        }
+    }
+
+    /// take all records out, usually used for flushing to persistent storage periodically
+    pub fn drain(&self) -> Vec<AuditRecord> {
+        self.records.lock().drain(..).collect()
+    }
+
+    /// count of records in store
+    pub fn len(&self) -> usize {
+        self.records.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.lock().is_empty()
+    }
+
+    fn push(&self, record: AuditRecord) {
+        if self.max_records == 0 {
+            return;
+        }
+
+        let mut records = self.records.lock();
+        while records.len() >= self.max_records {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+/// Layer which captures request bodies into an [AuditStore], for compliance audit
+///
+/// The whole body is buffered and handed to the inner service unchanged, only the stored copy
+/// is truncated. Bodies larger than [max_request_bytes](Self::max_request_bytes) are not
+/// buffered, they are streamed to the inner service and recorded as truncated.
+///
+/// This layer produces instances of the [RequestBodySniffer] service.
+///
+/// # Example
+/// ```
+/// # use spa_rs::routing::{post, Router};
+/// # use spa_rs::middleware::{AuditStore, RequestBodySnifferLayer};
+/// # use std::sync::Arc;
+/// #
+/// let store = Arc::new(AuditStore::new(10000));
+/// let app: Router = Router::new()
+///     .route("/transfer", post(|| async { "done" }))
+///     .layer(RequestBodySnifferLayer::new(store.clone(), 4096));
+///
+/// // flush periodically
+/// let records = store.drain();
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestBodySnifferLayer {
+    store: Arc<AuditStore>,
+    max_body_bytes: usize,
+    max_request_bytes: usize,
+}
+
+/// Default max request body size buffered by [RequestBodySnifferLayer]
+pub const DEFAULT_AUDIT_MAX_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+impl RequestBodySnifferLayer {
+    pub fn new(store: Arc<AuditStore>, max_body_bytes: usize) -> Self {
+        Self {
+            store,
+            max_body_bytes,
+            max_request_bytes: DEFAULT_AUDIT_MAX_REQUEST_BYTES,
+        }
+    }
+
+    /// don't buffer request body larger than `max_bytes`, default is
+    /// [DEFAULT_AUDIT_MAX_REQUEST_BYTES]
+    pub fn max_request_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_request_bytes = max_bytes;
+        self
+    }
+}
+
+impl<S> Layer<S> for RequestBodySnifferLayer {
+    type Service = RequestBodySniffer<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestBodySniffer {
+            inner,
+            store: self.store.clone(),
+            max_body_bytes: self.max_body_bytes,
+            max_request_bytes: self.max_request_bytes,
+        }
+    }
+}
+
+/// Service which captures request bodies, see [RequestBodySnifferLayer]
+#[derive(Clone, Debug)]
+pub struct RequestBodySniffer<S> {
+    inner: S,
+    store: Arc<AuditStore>,
+    max_body_bytes: usize,
+    max_request_bytes: usize,
+}
+
+impl<S> Service<Request> for RequestBodySniffer<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let store = self.store.clone();
+        let max_body_bytes = self.max_body_bytes;
+        let max_request_bytes = self.max_request_bytes;
+        let clone = self.inner.clone();
+        let mut inner = mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let client_ip = RealIp::from_extensions(request.extensions()).map(|ip| ip.0);
+            let request_id = request
+                .extensions()
+                .get::<RequestId>()
+                .and_then(|id| id.header_value().to_str().ok())
+                .map(ToString::to_string);
+            let (parts, body) = request.into_parts();
+            let (recorded, truncated, body) = match try_buffer_body(body, max_request_bytes).await {
+                Ok(Buffered::Full(body)) => (body.clone(), false, Body::from(body)),
+                Ok(Buffered::TooLarge { read, body }) => (read, true, body),
+                Err(e) => {
+                    return Ok((
+                        StatusCode::BAD_REQUEST,
+                        format!("read request body error: {}", e),
+                    )
+                        .into_response())
+                }
+            };
+
+            store.push(AuditRecord {
+                timestamp: SystemTime::now(),
+                method: parts.method.clone(),
+                uri: parts.uri.clone(),
+                truncated: truncated || recorded.len() > max_body_bytes,
+                body: recorded.slice(..recorded.len().min(max_body_bytes)),
+                client_ip,
+                request_id,
+            });
+
+            inner.call(Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AuditStore, RequestBodySnifferLayer};
+    use axum::{body::Body, extract::Request, routing::post, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use tower_http::request_id::{MakeRequestUuid, SetRequestIdLayer};
+
+    #[tokio::test]
+    async fn test_request_body_sniffer() {
+        let store = Arc::new(AuditStore::new(2));
+        let app = Router::new()
+            .route("/", post(|body: String| async move { body }))
+            .layer(RequestBodySnifferLayer::new(store.clone(), 4).max_request_bytes(8))
+            .layer(SetRequestIdLayer::new(
+                "x-correlation-id".parse().unwrap(),
+                MakeRequestUuid,
+            ));
+        let call = |body: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::post("/").body(Body::from(body)).unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert_eq!(call("dropped").await, "dropped");
+        assert_eq!(call("abc").await, "abc");
+        assert_eq!(store.len(), 2);
+        let records = store.drain();
+        assert_eq!(records[1].body, "abc");
+        assert!(!records[1].truncated);
+
+        assert_eq!(call("dropped").await, "dropped");
+        assert_eq!(call("abcdef").await, "abcdef");
+        // larger than max_request_bytes, passed through and recorded as truncated
+        assert_eq!(call("abcdefghijk").await, "abcdefghijk");
+        // the oldest record is dropped
+        assert_eq!(store.len(), 2);
+
+        let records = store.drain();
+        assert!(store.is_empty());
+        assert_eq!(records[0].body, "abcd");
+        assert!(records[0].truncated);
+        assert!(records[1].body.is_empty());
+        assert!(records[1].truncated);
+        assert!(records[1].request_id.is_some());
+        assert_eq!(records[1].method, "POST");
+    }
+}
@@ -0,0 +1,405 @@
+use axum::{
+    body::{to_bytes, Body, Bytes, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+
+/// Time to live of cached response, set it as a request extension to enable [CacheLayer]
+/// for specific routes
+///
+/// Note that the extension layer should be the outer one, so it's added **after** [CacheLayer]
+///
+/// # Example
+/// ```
+/// # use spa_rs::routing::{get, Router};
+/// # use spa_rs::Extension;
+/// # use spa_rs::middleware::{CacheLayer, CacheStore, CacheTtl};
+/// # use std::{sync::Arc, time::Duration};
+/// #
+/// let store = Arc::new(CacheStore::default());
+/// let app: Router = Router::new()
+///     .route("/menu", get(|| async { "expensive menu" }))
+///     .layer(CacheLayer::new(store))
+///     .layer(Extension(CacheTtl(Duration::from_secs(60))));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct CacheTtl(pub Duration);
+
+/// Default max count of responses in [CacheStore]
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 10000;
+/// Default max body size of a response cached by [CacheStore], larger ones are not cached
+pub const DEFAULT_CACHE_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// A cached response
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub expires_at: Instant,
+    /// request headers named in `Vary` of the response, and their values when cached
+    pub vary: Vec<(HeaderName, Option<HeaderValue>)>,
+}
+
+impl CachedResponse {
+    fn to_response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+
+    fn vary_matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+}
+
+/// Storage of [CacheLayer], keyed by request method and uri
+///
+/// It holds at most [DEFAULT_CACHE_MAX_ENTRIES] responses by default, expired ones are swept
+/// when it's full, and new responses are not cached if it's still full after that.
+#[derive(Debug)]
+pub struct CacheStore {
+    inner: DashMap<String, CachedResponse>,
+    default_ttl: Option<Duration>,
+    max_entries: usize,
+    max_body_bytes: usize,
+    last_sweep: Mutex<Instant>,
+}
+
+impl Default for CacheStore {
+    fn default() -> Self {
+        Self {
+            inner: DashMap::new(),
+            default_ttl: None,
+            max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+            max_body_bytes: DEFAULT_CACHE_MAX_BODY_BYTES,
+            last_sweep: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl CacheStore {
+    /// cache all GET requests even if no [CacheTtl] extension found
+    pub fn with_default_ttl(ttl: Duration) -> Self {
+        Self {
+            default_ttl: Some(ttl),
+            ..Default::default()
+        }
+    }
+
+    /// hold at most `max_entries` responses, default is [DEFAULT_CACHE_MAX_ENTRIES]
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// responses larger than `max_bytes` or with unknown size are not cached, default is
+    /// [DEFAULT_CACHE_MAX_BODY_BYTES]
+    pub fn max_body_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_body_bytes = max_bytes;
+        self
+    }
+
+    /// get an unexpired cached response
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let cached = self.inner.get(key)?.clone();
+        if cached.expires_at <= Instant::now() {
+            self.inner.remove(key);
+            return None;
+        }
+
+        Some(cached)
+    }
+
+    /// count of cached responses, including expired ones not swept yet
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// remove cached response of `key`, key is in `"{method} {uri}"` format
+    pub fn invalidate(&self, key: &str) {
+        self.inner.remove(key);
+    }
+
+    /// remove all expired responses
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        self.inner.retain(|_, v| v.expires_at > now);
+    }
+
+    /// remove all cached responses
+    pub fn clear(&self) {
+        self.inner.clear();
+    }
+
+    fn key(request: &Request) -> String {
+        format!("{} {}", request.method(), request.uri())
+    }
+
+    fn insert(&self, key: String, cached: CachedResponse) {
+        if self.inner.len() >= self.max_entries && !self.inner.contains_key(&key) {
+            // sweeping is O(n), do it at most once a second
+            let mut last_sweep = self.last_sweep.lock();
+            if last_sweep.elapsed() >= Duration::from_secs(1) {
+                *last_sweep = Instant::now();
+                self.purge_expired();
+            }
+            if self.inner.len() >= self.max_entries {
+                return;
+            }
+        }
+        self.inner.insert(key, cached);
+    }
+}
+
+/// requests carrying credentials get responses of that user, which must not be shared
+fn cacheable_request(request: &Request) -> bool {
+    request.method() == Method::GET
+        && !request.headers().contains_key(header::AUTHORIZATION)
+        && !request.headers().contains_key(header::COOKIE)
+}
+
+fn cacheable_response(response: &Response) -> bool {
+    let headers = response.headers();
+    let private = headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|d| {
+            let d = d.trim();
+            d.eq_ignore_ascii_case("private") || d.eq_ignore_ascii_case("no-store")
+        });
+    response.status() == StatusCode::OK && !headers.contains_key(header::SET_COOKIE) && !private
+}
+
+/// request headers named in `Vary`, [None] if it's `*` or invalid
+fn vary_headers(
+    response: &Response,
+    request: &HeaderMap,
+) -> Option<Vec<(HeaderName, Option<HeaderValue>)>> {
+    let mut vary = Vec::new();
+    for value in response.headers().get_all(header::VARY) {
+        for name in value.to_str().ok()?.split(',') {
+            let name = name.trim();
+            if name == "*" {
+                return None;
+            }
+            let name: HeaderName = name.parse().ok()?;
+            let value = request.get(&name).cloned();
+            vary.push((name, value));
+        }
+    }
+    Some(vary)
+}
+
+/// Layer which cache successful GET responses in a [CacheStore]
+///
+/// Only `200 OK` responses are cached, time to live is specificed by [CacheTtl]. Requests
+/// with `Authorization` or `Cookie`, and responses with `Set-Cookie`, `Vary: *` or
+/// `Cache-Control: private` or `no-store` are never cached. Other headers named in `Vary`
+/// must match the cached request to hit the cache.
+///
+/// This layer produces instances of the [Cache] service.
+#[derive(Clone, Debug)]
+pub struct CacheLayer {
+    store: Arc<CacheStore>,
+}
+
+impl CacheLayer {
+    pub fn new(store: Arc<CacheStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl<S> Layer<S> for CacheLayer {
+    type Service = Cache<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Cache {
+            inner,
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// Service which cache responses, see [CacheLayer]
+#[derive(Clone, Debug)]
+pub struct Cache<S> {
+    inner: S,
+    store: Arc<CacheStore>,
+}
+
+impl<S> Service<Request> for Cache<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let ttl = request
+            .extensions()
+            .get::<CacheTtl>()
+            .map(|t| t.0)
+            .or(self.store.default_ttl);
+        let ttl = match ttl {
+            Some(ttl) if cacheable_request(&request) => ttl,
+            _ => return Box::pin(self.inner.call(request)),
+        };
+
+        let key = CacheStore::key(&request);
+        if let Some(cached) = self.store.get(&key) {
+            if cached.vary_matches(request.headers()) {
+                return Box::pin(async move { Ok(cached.to_response()) });
+            }
+        }
+
+        let store = self.store.clone();
+        let request_headers = request.headers().clone();
+        let clone = self.inner.clone();
+        let mut inner = mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let response = inner.call(request).await?;
+            let size = response.body().size_hint().upper();
+            if !cacheable_response(&response)
+                || size.is_none_or(|size| size > store.max_body_bytes as u64)
+            {
+                return Ok(response);
+            }
+            let Some(vary) = vary_headers(&response, &request_headers) else {
+                return Ok(response);
+            };
+
+            let (parts, body) = response.into_parts();
+            let body = match to_bytes(body, store.max_body_bytes).await {
+                Ok(body) => body,
+                Err(e) => {
+                    return Ok((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("buffer response body error: {}", e),
+                    )
+                        .into_response())
+                }
+            };
+
+            let cached = CachedResponse {
+                headers: parts.headers.clone(),
+                body: body.clone(),
+                expires_at: Instant::now() + ttl,
+                vary,
+            };
+            store.insert(key, cached);
+            Ok(Response::from_parts(parts, Body::from(body)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CacheLayer, CacheStore};
+    use axum::{
+        body::Body,
+        http::{header, Request},
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_cache() {
+        let store = Arc::new(CacheStore::with_default_ttl(Duration::from_secs(60)).max_entries(2));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let handler = move || {
+            let calls = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move { calls.to_string() }
+        };
+        let app = Router::new()
+            .route("/public", get(handler.clone()))
+            .route(
+                "/session",
+                get(|| async { ([(header::SET_COOKIE, "id=1")], "session").into_response() }),
+            )
+            .route(
+                "/lang",
+                get(handler).layer(axum::middleware::map_response(
+                    |mut response: axum::response::Response| async move {
+                        let vary = header::HeaderValue::from_static("accept-language");
+                        response.headers_mut().insert(header::VARY, vary);
+                        response
+                    },
+                )),
+            )
+            .layer(CacheLayer::new(store.clone()));
+        let call = |uri: &str, headers: &[(header::HeaderName, &str)]| {
+            let mut request = Request::builder().uri(uri);
+            for (name, value) in headers {
+                request = request.header(name, *value);
+            }
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+
+        assert_eq!(call("/public", &[]).await, "1");
+        assert_eq!(call("/public", &[]).await, "1");
+        // credentials are never cached or served from cache
+        assert_eq!(call("/public", &[(header::COOKIE, "id=2")]).await, "2");
+        assert_eq!(
+            call("/public?a", &[(header::AUTHORIZATION, "x")]).await,
+            "3"
+        );
+        assert_eq!(call("/session", &[]).await, "session");
+        assert_eq!(store.len(), 1);
+
+        let de = [(header::ACCEPT_LANGUAGE, "de")];
+        assert_eq!(call("/lang", &de).await, "4");
+        assert_eq!(call("/lang", &de).await, "4");
+        assert_eq!(call("/lang", &[(header::ACCEPT_LANGUAGE, "en")]).await, "5");
+
+        // full
+        assert_eq!(call("/public?b", &[]).await, "6");
+        assert_eq!(call("/public?b", &[]).await, "7");
+        assert_eq!(store.len(), 2);
+    }
+}
@@ -0,0 +1,192 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    response::Response,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{Layer, Service};
+
+/// `Cache-Control` policy of a kind of files, see [CacheRuleSet]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// `no-cache`, clients always revalidate
+    NoCache,
+    /// `no-store`
+    NoStore,
+    /// `public, max-age=<secs>`
+    MaxAge(Duration),
+    /// `public, max-age=31536000, immutable`, for hashed bundles which never change
+    Immutable,
+}
+
+impl CachePolicy {
+    fn header_value(&self) -> HeaderValue {
+        match self {
+            CachePolicy::NoCache => HeaderValue::from_static("no-cache"),
+            CachePolicy::NoStore => HeaderValue::from_static("no-store"),
+            CachePolicy::MaxAge(age) => {
+                HeaderValue::from_str(&format!("public, max-age={}", age.as_secs()))
+                    .expect("max-age is a valid header value")
+            }
+            CachePolicy::Immutable => {
+                HeaderValue::from_static("public, max-age=31536000, immutable")
+            }
+        }
+    }
+}
+
+/// `Cache-Control` policies keyed by file extension
+///
+/// # Example
+/// ```
+/// # use spa_rs::middleware::{CachePolicy, CacheRuleSet};
+/// # use std::time::Duration;
+/// let rules = CacheRuleSet::default_spa_rules()
+///     .for_extension("pdf", CachePolicy::MaxAge(Duration::from_secs(3600)));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CacheRuleSet {
+    rules: HashMap<String, CachePolicy>,
+}
+
+impl CacheRuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `policy` for files with `ext` (without dot, case insensitive)
+    pub fn for_extension(mut self, ext: &str, policy: CachePolicy) -> Self {
+        self.rules
+            .insert(ext.trim_start_matches('.').to_ascii_lowercase(), policy);
+        self
+    }
+
+    /// Rules for typical SPA assets: html and json are `no-cache`, js and css bundles are
+    /// immutable, images and fonts are cached for a year
+    pub fn default_spa_rules() -> Self {
+        const YEAR: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+        let mut rules = Self::new();
+        for ext in ["html", "htm", "json", "webmanifest"] {
+            rules = rules.for_extension(ext, CachePolicy::NoCache);
+        }
+        for ext in ["js", "mjs", "css", "wasm"] {
+            rules = rules.for_extension(ext, CachePolicy::Immutable);
+        }
+        for ext in [
+            "png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "ico", "woff", "woff2", "ttf",
+            "otf",
+        ] {
+            rules = rules.for_extension(ext, CachePolicy::MaxAge(YEAR));
+        }
+        rules
+    }
+
+    /// Rules of `other` take priority over the ones of `self`
+    pub(crate) fn merge(mut self, other: CacheRuleSet) -> Self {
+        self.rules.extend(other.rules);
+        self
+    }
+
+    /// The policy of request `path`, directories are treated as `index.html`
+    pub fn policy(&self, path: &str) -> Option<CachePolicy> {
+        let ext = if path.ends_with('/') {
+            "html".to_string()
+        } else {
+            Path::new(path).extension()?.to_str()?.to_ascii_lowercase()
+        };
+        self.rules.get(&ext).copied()
+    }
+}
+
+/// Layer which sets `Cache-Control` of successful responses by the extension of request path
+///
+/// Responses of paths matching no rule are untouched.
+///
+/// This layer produces instances of the [CacheControl] service.
+#[derive(Clone, Debug)]
+pub struct CacheControlLayer {
+    rules: Arc<CacheRuleSet>,
+}
+
+impl CacheControlLayer {
+    pub fn new(rules: CacheRuleSet) -> Self {
+        Self {
+            rules: Arc::new(rules),
+        }
+    }
+}
+
+impl<S> Layer<S> for CacheControlLayer {
+    type Service = CacheControl<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CacheControl {
+            inner,
+            rules: self.rules.clone(),
+        }
+    }
+}
+
+/// Service which sets `Cache-Control` of responses, see [CacheControlLayer]
+#[derive(Clone, Debug)]
+pub struct CacheControl<S> {
+    inner: S,
+    rules: Arc<CacheRuleSet>,
+}
+
+impl<S, B> Service<Request> for CacheControl<S>
+where
+    S: Service<Request, Response = Response<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let policy = self.rules.policy(request.uri().path());
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            let status = response.status();
+            if let Some(policy) = policy.filter(|_| status.is_success() || status.as_u16() == 304) {
+                response
+                    .headers_mut()
+                    .insert(header::CACHE_CONTROL, policy.header_value());
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CachePolicy, CacheRuleSet};
+
+    #[test]
+    fn test_cache_rules() {
+        let rules = CacheRuleSet::default_spa_rules().for_extension(".PDF", CachePolicy::NoStore);
+        assert_eq!(rules.policy("/index.html"), Some(CachePolicy::NoCache));
+        assert_eq!(rules.policy("/docs/"), Some(CachePolicy::NoCache));
+        assert_eq!(
+            rules.policy("/assets/app.3f2a.JS"),
+            Some(CachePolicy::Immutable)
+        );
+        assert_eq!(rules.policy("/report.pdf"), Some(CachePolicy::NoStore));
+        assert_eq!(rules.policy("/download"), None);
+        assert_eq!(rules.policy("/data.bin"), None);
+    }
+}
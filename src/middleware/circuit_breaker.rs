@@ -0,0 +1,295 @@
+use super::under_prefix;
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+
+/// Options of [CircuitBreakerLayer]
+#[derive(Clone, Copy, Debug)]
+pub struct CircuitBreakerConfig {
+    /// consecutive `5xx` responses to open the circuit
+    pub failure_threshold: u32,
+    /// successful probe responses to close the circuit again
+    pub success_threshold: u32,
+    /// how long the circuit stays open before probing
+    pub timeout: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            success_threshold: 1,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CircuitState {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// `probe` is the start time of the in-flight probe request
+    HalfOpen {
+        successes: u32,
+        probe: Option<Instant>,
+    },
+}
+
+/// Layer which stops calling a failing backend for a while
+///
+/// After `failure_threshold` consecutive `5xx` responses the circuit opens, requests get
+/// `503 Service Unavailable` with `Retry-After` immediately. When `timeout` elapsed the
+/// circuit is half-open, one probe request is let through at a time, and it is closed again
+/// after `success_threshold` successful probes, or opened again on a failed one.
+///
+/// This layer produces instances of the [CircuitBreaker] service.
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerLayer {
+    config: CircuitBreakerConfig,
+    path_prefix: Option<String>,
+    state: Arc<Mutex<CircuitState>>,
+}
+
+impl CircuitBreakerLayer {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            path_prefix: None,
+            state: Arc::new(Mutex::new(CircuitState::Closed { failures: 0 })),
+        }
+    }
+
+    /// only requests of `prefix` and paths under it are guarded, others are passed through
+    pub fn path_prefix(mut self, prefix: &str) -> Self {
+        self.path_prefix = Some(prefix.trim_end_matches('/').to_string());
+        self
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Service = CircuitBreaker<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CircuitBreaker {
+            inner,
+            config: self.config,
+            path_prefix: self.path_prefix.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Service which guards the inner service with a circuit breaker, see [CircuitBreakerLayer]
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker<S> {
+    inner: S,
+    config: CircuitBreakerConfig,
+    path_prefix: Option<String>,
+    state: Arc<Mutex<CircuitState>>,
+}
+
+impl<S> CircuitBreaker<S> {
+    /// returns whether the request is a probe, or the seconds to retry after if rejected
+    fn admit(&self) -> Result<bool, u64> {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+        if let CircuitState::Open { until } = *state {
+            if now < until {
+                return Err((until - now).as_secs_f64().ceil().max(1.0) as u64);
+            }
+            *state = CircuitState::HalfOpen {
+                successes: 0,
+                probe: None,
+            };
+        }
+
+        match &mut *state {
+            CircuitState::Closed { .. } => Ok(false),
+            // a probe never finished (such as cancelled) is given up after `timeout`
+            CircuitState::HalfOpen {
+                probe: Some(started),
+                ..
+            } if now.duration_since(*started) < self.config.timeout => Err(1),
+            CircuitState::HalfOpen { probe, .. } => {
+                *probe = Some(now);
+                Ok(true)
+            }
+            CircuitState::Open { .. } => unreachable!(),
+        }
+    }
+}
+
+fn record(state: &Mutex<CircuitState>, config: &CircuitBreakerConfig, probing: bool, failed: bool) {
+    let mut state = state.lock();
+    let open = CircuitState::Open {
+        until: Instant::now() + config.timeout,
+    };
+    *state = match *state {
+        CircuitState::Closed { .. } if !failed => CircuitState::Closed { failures: 0 },
+        CircuitState::Closed { failures } if failures + 1 >= config.failure_threshold => open,
+        CircuitState::Closed { failures } => CircuitState::Closed {
+            failures: failures + 1,
+        },
+        CircuitState::HalfOpen { .. } if probing && failed => open,
+        CircuitState::HalfOpen { successes, .. }
+            if probing && successes + 1 >= config.success_threshold =>
+        {
+            CircuitState::Closed { failures: 0 }
+        }
+        CircuitState::HalfOpen { successes, .. } if probing => CircuitState::HalfOpen {
+            successes: successes + 1,
+            probe: None,
+        },
+        // responses of requests admitted before the state changed
+        state => state,
+    };
+}
+
+impl<S> Service<Request> for CircuitBreaker<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let guarded = match &self.path_prefix {
+            Some(prefix) => under_prefix(request.uri().path(), prefix),
+            None => true,
+        };
+        if !guarded {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let probing = match self.admit() {
+            Ok(probing) => probing,
+            Err(retry_after) => {
+                return Box::pin(async move {
+                    Ok((
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        [(header::RETRY_AFTER, HeaderValue::from(retry_after))],
+                        "service unavailable, circuit is open",
+                    )
+                        .into_response())
+                })
+            }
+        };
+
+        let state = self.state.clone();
+        let config = self.config;
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            record(
+                &state,
+                &config,
+                probing,
+                response.status().is_server_error(),
+            );
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CircuitBreakerConfig, CircuitBreakerLayer};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let backend = failing.clone();
+        let app = Router::new()
+            .route(
+                "/api",
+                get(move || async move {
+                    if backend.load(Ordering::SeqCst) {
+                        StatusCode::BAD_GATEWAY
+                    } else {
+                        StatusCode::OK
+                    }
+                }),
+            )
+            .route(
+                "/other",
+                get(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            )
+            .layer(
+                CircuitBreakerLayer::new(CircuitBreakerConfig {
+                    failure_threshold: 2,
+                    success_threshold: 2,
+                    timeout: Duration::from_millis(100),
+                })
+                .path_prefix("/api"),
+            );
+        let call = |uri: &str| {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap() }
+        };
+
+        assert_eq!(
+            call("/other").await.status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            call("/other").await.status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(call("/api").await.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(call("/api").await.status(), StatusCode::BAD_GATEWAY);
+        let response = call("/api").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+
+        // failed probe opens the circuit again
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(call("/api").await.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(call("/api").await.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        failing.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(call("/api").await.status(), StatusCode::OK);
+        assert_eq!(call("/api").await.status(), StatusCode::OK);
+        // closed, failures are counted from zero again
+        failing.store(true, Ordering::SeqCst);
+        assert_eq!(call("/api").await.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(call("/api").await.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(call("/api").await.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,64 @@
+use axum::Router;
+use tower_http::{compression::CompressionLayer, CompressionLevel};
+
+/// Quality of response compression, see [SpaServer::response_compression_with_options](crate::SpaServer::response_compression_with_options)
+///
+/// Algorithms not specified use their default quality. When client accepts multiple algorithms,
+/// brotli is preferred, then zstd, then gzip.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompressionOptions {
+    gzip: Option<u32>,
+    brotli: Option<u32>,
+    zstd: Option<i32>,
+}
+
+impl CompressionOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// gzip level, clamped into `1..=9`
+    pub fn gzip_level(mut self, level: u32) -> Self {
+        self.gzip = Some(level.clamp(1, 9));
+        self
+    }
+
+    /// brotli quality, clamped into `0..=11`
+    pub fn brotli_quality(mut self, quality: u32) -> Self {
+        self.brotli = Some(quality.min(11));
+        self
+    }
+
+    /// zstd level, clamped into `-22..=22`
+    pub fn zstd_level(mut self, level: i32) -> Self {
+        self.zstd = Some(level.clamp(-22, 22));
+        self
+    }
+
+    fn level(level: Option<i32>) -> CompressionLevel {
+        level.map_or(CompressionLevel::Default, CompressionLevel::Precise)
+    }
+
+    /// One layer per algorithm, since [CompressionLayer] takes only one quality for all of them
+    ///
+    /// Responses already compressed by inner layer are skipped by outer ones, so the innermost
+    /// one is preferred.
+    pub(crate) fn apply(self, router: Router) -> Router {
+        let only = CompressionLayer::new()
+            .no_br()
+            .no_gzip()
+            .no_zstd()
+            .no_deflate();
+        router
+            .layer(
+                only.clone()
+                    .br(true)
+                    .quality(Self::level(self.brotli.map(|q| q as i32))),
+            )
+            .layer(only.clone().zstd(true).quality(Self::level(self.zstd)))
+            .layer(
+                only.gzip(true)
+                    .quality(Self::level(self.gzip.map(|l| l as i32))),
+            )
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::{env, str::FromStr};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+/// Environment variable of allowed origins, comma separated or `*`
+pub const CORS_ORIGINS_ENV: &str = "SPA_CORS_ORIGINS";
+/// Environment variable of allowed methods, comma separated or `*`, default is `GET,HEAD`
+pub const CORS_METHODS_ENV: &str = "SPA_CORS_METHODS";
+/// Environment variable of allowed request headers, comma separated or `*`
+pub const CORS_HEADERS_ENV: &str = "SPA_CORS_HEADERS";
+
+/// Build cors layer from environment variables, see [SpaServer::cors_from_env](crate::SpaServer::cors_from_env)
+pub(crate) fn cors_from_env() -> Result<CorsLayer> {
+    cors_from(
+        env::var(CORS_ORIGINS_ENV).ok().as_deref(),
+        env::var(CORS_METHODS_ENV).ok().as_deref(),
+        env::var(CORS_HEADERS_ENV).ok().as_deref(),
+    )
+}
+
+fn cors_from(
+    origins: Option<&str>,
+    methods: Option<&str>,
+    headers: Option<&str>,
+) -> Result<CorsLayer> {
+    let origins = match origins {
+        Some("*") => AllowOrigin::any(),
+        Some(origins) => AllowOrigin::list(parse_list::<HeaderValue>(origins, CORS_ORIGINS_ENV)?),
+        None => AllowOrigin::list([]),
+    };
+    let methods = match methods {
+        Some("*") => AllowMethods::any(),
+        Some(methods) => AllowMethods::list(parse_list::<Method>(methods, CORS_METHODS_ENV)?),
+        None => AllowMethods::list([Method::GET, Method::HEAD]),
+    };
+    let headers = match headers {
+        Some("*") => AllowHeaders::any(),
+        Some(headers) => AllowHeaders::list(parse_list::<HeaderName>(headers, CORS_HEADERS_ENV)?),
+        None => AllowHeaders::list([]),
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers))
+}
+
+fn parse_list<T>(list: &str, name: &str) -> Result<Vec<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse()
+                .with_context(|| format!("invalid value {:?} in {}", item, name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::cors_from;
+
+    #[test]
+    fn test_cors_from() {
+        assert!(cors_from(None, None, None).is_ok());
+        assert!(cors_from(Some("*"), Some("*"), Some("*")).is_ok());
+        assert!(cors_from(
+            Some("https://a.com, https://b.com"),
+            Some("GET,POST"),
+            Some("x-token")
+        )
+        .is_ok());
+        assert!(cors_from(None, Some("GET,BAD METHOD"), None).is_err());
+        assert!(cors_from(None, None, Some("bad header")).is_err());
+    }
+}
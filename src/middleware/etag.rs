@@ -0,0 +1,174 @@
+use super::SpaFallback;
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+    BoxError,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// How the ETag of embedded SPA files is generated, see [SpaServer::static_file_etag_strategy](crate::SpaServer::static_file_etag_strategy)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EtagStrategy {
+    /// SHA-256 of file content, computed when compiling by rust-embed
+    ContentHash,
+    /// modified time of the released file in unix milliseconds, formatted as hex
+    LastModified,
+}
+
+/// Layer which sets `ETag` of static files, and responds `304 Not Modified` if it
+/// matches `If-None-Match`
+///
+/// `etags` maps request path (such as `/assets/index.js`) to the ETag value without quotes.
+/// Requests to directory use the ETag of `index.html` in it, and SPA `index.html`
+/// fallback uses the ETag of `/index.html`.
+///
+/// This layer produces instances of the [Etag] service.
+#[derive(Clone, Debug)]
+pub struct EtagLayer {
+    etags: Arc<HashMap<String, String>>,
+}
+
+impl EtagLayer {
+    pub fn new(etags: HashMap<String, String>) -> Self {
+        Self {
+            etags: Arc::new(etags),
+        }
+    }
+}
+
+impl<S> Layer<S> for EtagLayer {
+    type Service = Etag<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Etag {
+            inner,
+            etags: self.etags.clone(),
+        }
+    }
+}
+
+/// Service which sets `ETag` of static files, see [EtagLayer]
+#[derive(Clone, Debug)]
+pub struct Etag<S> {
+    inner: S,
+    etags: Arc<HashMap<String, String>>,
+}
+
+fn lookup<'a>(
+    etags: &'a HashMap<String, String>,
+    path: &str,
+    fallback: bool,
+) -> Option<&'a String> {
+    if fallback {
+        return etags.get("/index.html");
+    }
+    if path.ends_with('/') {
+        return etags.get(&format!("{}index.html", path));
+    }
+    etags.get(path)
+}
+
+/// Set `ETag` of `response`, or replace it with `304 Not Modified` if `if_none_match` matches
+pub(crate) fn with_etag(
+    mut response: Response,
+    etag: HeaderValue,
+    if_none_match: Option<HeaderValue>,
+) -> Response {
+    if if_none_match.is_some_and(|v| matches(&v, &etag)) {
+        let mut not_modified = Response::new(Body::empty());
+        *not_modified.status_mut() = StatusCode::NOT_MODIFIED;
+        for name in [header::CACHE_CONTROL, header::LAST_MODIFIED, header::VARY] {
+            if let Some(value) = response.headers().get(&name) {
+                not_modified.headers_mut().insert(name, value.clone());
+            }
+        }
+        not_modified.headers_mut().insert(header::ETAG, etag);
+        return not_modified;
+    }
+
+    response.headers_mut().insert(header::ETAG, etag);
+    response
+}
+
+fn matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+impl<S, B> Service<Request> for Etag<S>
+where
+    S: Service<Request, Response = Response<B>>,
+    S::Future: Send + 'static,
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let path = request.uri().path().to_string();
+        let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+        let etags = self.etags.clone();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?.map(Body::new);
+            if response.status() != StatusCode::OK || response.headers().contains_key(header::ETAG)
+            {
+                return Ok(response);
+            }
+
+            let fallback = response.extensions().get::<SpaFallback>().is_some();
+            let Some(etag) = lookup(&etags, &path, fallback) else {
+                return Ok(response);
+            };
+            // precompressed file is a different representation
+            let etag = match response.headers().get(header::CONTENT_ENCODING) {
+                Some(encoding) => format!("\"{}-{}\"", etag, encoding.to_str().unwrap_or("enc")),
+                None => format!("\"{}\"", etag),
+            };
+            let Ok(etag) = HeaderValue::from_str(&etag) else {
+                return Ok(response);
+            };
+
+            Ok(with_etag(response, etag, if_none_match))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::matches;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_matches() {
+        let etag = HeaderValue::from_static("\"abc\"");
+        assert!(matches(&HeaderValue::from_static("\"abc\""), &etag));
+        assert!(matches(
+            &HeaderValue::from_static("\"x\", W/\"abc\""),
+            &etag
+        ));
+        assert!(matches(&HeaderValue::from_static("*"), &etag));
+        assert!(!matches(&HeaderValue::from_static("\"abcd\""), &etag));
+    }
+}
@@ -0,0 +1,131 @@
+use super::etag::with_etag;
+use anyhow::{Context as _, Result};
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+    BoxError,
+};
+use dashmap::DashMap;
+use log::{info, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tower::{Layer, Service};
+
+/// Layer which watches files in `dir`, and sets `ETag` changed with file versions, see
+/// [SpaServer::hot_reload_static_path](crate::SpaServer::hot_reload_static_path)
+///
+/// Responses are set `Cache-Control: no-cache`, so clients always revalidate and get
+/// `304 Not Modified` until the file changed.
+#[derive(Clone)]
+pub(crate) struct HotReloadLayer {
+    dir: PathBuf,
+    /// versions of changed files, files never changed are version 0
+    versions: Arc<DashMap<PathBuf, u64>>,
+    /// distinguishes versions of different runs
+    epoch: u128,
+    _watcher: Arc<RecommendedWatcher>,
+}
+
+impl HotReloadLayer {
+    pub(crate) fn watch(dir: &Path) -> Result<Self> {
+        let dir = dir
+            .canonicalize()
+            .with_context(|| format!("hot reload path {} error", dir.display()))?;
+        let versions = Arc::new(DashMap::new());
+        let changed = versions.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    for path in event.paths {
+                        info!("static file {} changed", path.display());
+                        *changed.entry(path).or_insert(0) += 1;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("watch static files error: {}", e),
+            })?;
+        watcher.watch(&dir, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            dir,
+            versions,
+            epoch: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            _watcher: Arc::new(watcher),
+        })
+    }
+
+    fn etag(&self, path: &str) -> Option<HeaderValue> {
+        let mut file = self.dir.join(path.trim_start_matches('/'));
+        if path.ends_with('/') || path.is_empty() {
+            file.push("index.html");
+        }
+        let version = self.versions.get(&file).map(|v| *v).unwrap_or_default();
+        HeaderValue::from_str(&format!("\"{:x}-{}\"", self.epoch, version)).ok()
+    }
+}
+
+impl<S> Layer<S> for HotReloadLayer {
+    type Service = HotReload<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HotReload {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service which sets `ETag` of watched files, see [HotReloadLayer]
+#[derive(Clone)]
+pub(crate) struct HotReload<S> {
+    inner: S,
+    layer: HotReloadLayer,
+}
+
+impl<S, B> Service<Request> for HotReload<S>
+where
+    S: Service<Request, Response = Response<B>>,
+    S::Future: Send + 'static,
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let etag = self.layer.etag(request.uri().path());
+        let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?.map(Body::new);
+            if response.status() != StatusCode::OK {
+                return Ok(response);
+            }
+
+            response
+                .headers_mut()
+                .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+            Ok(match etag {
+                Some(etag) => with_etag(response, etag, if_none_match),
+                None => response,
+            })
+        })
+    }
+}
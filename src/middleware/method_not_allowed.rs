@@ -0,0 +1,46 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    handler::Handler,
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+/// Methods allowed by the matched route, can access by Extension in the handler of
+/// [SpaServer::method_not_allowed_handler](crate::SpaServer::method_not_allowed_handler)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllowedMethods(pub Vec<Method>);
+
+/// Replace `405 Method Not Allowed` response with the one produced by `handler`
+///
+/// `handler` is called with a copy of the request without body, and [AllowedMethods]
+/// parsed from `Allow` header of the original response.
+pub(crate) async fn method_not_allowed<H, T>(handler: H, request: Request, next: Next) -> Response
+where
+    H: Handler<T, ()>,
+    T: 'static,
+{
+    let mut copy = Request::new(Body::empty());
+    *copy.method_mut() = request.method().clone();
+    *copy.uri_mut() = request.uri().clone();
+    *copy.version_mut() = request.version();
+    *copy.headers_mut() = request.headers().clone();
+    *copy.extensions_mut() = request.extensions().clone();
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let allowed = response
+        .headers()
+        .get_all(header::ALLOW)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|m| m.trim().parse().ok())
+        .collect();
+    copy.extensions_mut().insert(AllowedMethods(allowed));
+    handler.call(copy, ()).await
+}
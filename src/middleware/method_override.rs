@@ -0,0 +1,172 @@
+use axum::{
+    extract::Request,
+    http::{header::HeaderName, Method},
+    response::Response,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Default header carrying the overridden method
+pub const METHOD_OVERRIDE_HEADER: &str = "x-http-method-override";
+
+/// Layer which replaces request method with the one in override header or `_method` query param
+///
+/// `mapping` maps the overridden method to the method the request must arrive with, such as
+/// `DELETE → POST`, so a `POST` request with `X-HTTP-Method-Override: DELETE` is routed as
+/// `DELETE`. Overrides not in the mapping are ignored, the header wins over query param.
+///
+/// Method must be changed before routing, so wrap the whole router with this layer instead of
+/// using `Router::layer`, which applies after the route is matched.
+///
+/// This layer produces instances of the [MethodOverride] service.
+#[derive(Clone, Debug)]
+pub struct MethodOverrideLayer {
+    header: HeaderName,
+    mapping: Arc<HashMap<Method, Method>>,
+}
+
+impl MethodOverrideLayer {
+    pub fn new(mapping: HashMap<Method, Method>) -> Self {
+        Self {
+            header: HeaderName::from_static(METHOD_OVERRIDE_HEADER),
+            mapping: Arc::new(mapping),
+        }
+    }
+
+    /// `PUT`, `PATCH` and `DELETE` can be tunneled through `POST`
+    pub fn post_tunneling() -> Self {
+        Self::new(
+            [Method::PUT, Method::PATCH, Method::DELETE]
+                .into_iter()
+                .map(|m| (m, Method::POST))
+                .collect(),
+        )
+    }
+
+    /// read overridden method from `header` instead of [METHOD_OVERRIDE_HEADER]
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+impl<S> Layer<S> for MethodOverrideLayer {
+    type Service = MethodOverride<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodOverride {
+            inner,
+            header: self.header.clone(),
+            mapping: self.mapping.clone(),
+        }
+    }
+}
+
+/// Service which overrides request method, see [MethodOverrideLayer]
+#[derive(Clone, Debug)]
+pub struct MethodOverride<S> {
+    inner: S,
+    header: HeaderName,
+    mapping: Arc<HashMap<Method, Method>>,
+}
+
+impl<S> MethodOverride<S> {
+    fn overridden(&self, request: &Request) -> Option<Method> {
+        let from_header = request
+            .headers()
+            .get(&self.header)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let from_query = || {
+            request
+                .uri()
+                .query()?
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("_method=").map(|m| m.to_string()))
+        };
+        let method: Method = from_header
+            .or_else(from_query)?
+            .trim()
+            .to_ascii_uppercase()
+            .parse()
+            .ok()?;
+
+        (self.mapping.get(&method) == Some(request.method())).then_some(method)
+    }
+}
+
+impl<S> Service<Request> for MethodOverride<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        if let Some(method) = self.overridden(&request) {
+            *request.method_mut() = method;
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MethodOverrideLayer;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+        routing::delete,
+        Router,
+    };
+    use tower::{Layer, ServiceExt};
+
+    #[tokio::test]
+    async fn test_method_override() {
+        // method must be changed before routing, so wrap the whole router
+        let app = MethodOverrideLayer::post_tunneling()
+            .layer(Router::new().route("/item", delete(|| async { "deleted" })));
+        let status = |method: Method, uri: &str, header: Option<&str>| {
+            let mut request = Request::builder().method(method).uri(uri);
+            if let Some(header) = header {
+                request = request.header("x-http-method-override", header);
+            }
+            let app = app.clone();
+            async move {
+                app.oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(
+            status(Method::POST, "/item", Some("delete")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(Method::POST, "/item?a=1&_method=DELETE", None).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(Method::GET, "/item", Some("DELETE")).await,
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            status(Method::POST, "/item", None).await,
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! A series of tower middlewares used by [SpaServer](crate::SpaServer), they can also be
+//! used in any axum router directly.
+//!
+pub use audit::{
+    AuditRecord, AuditStore, RequestBodySniffer, RequestBodySnifferLayer,
+    DEFAULT_AUDIT_MAX_REQUEST_BYTES,
+};
+pub use cache::{
+    Cache, CacheLayer, CacheStore, CacheTtl, CachedResponse, DEFAULT_CACHE_MAX_BODY_BYTES,
+    DEFAULT_CACHE_MAX_ENTRIES,
+};
+pub use cache_control::{CacheControl, CacheControlLayer, CachePolicy, CacheRuleSet};
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitBreakerLayer};
+pub use compression::CompressionOptions;
+pub use cors::{CORS_HEADERS_ENV, CORS_METHODS_ENV, CORS_ORIGINS_ENV};
+pub use etag::{Etag, EtagLayer, EtagStrategy};
+pub use method_not_allowed::AllowedMethods;
+pub use method_override::{MethodOverride, MethodOverrideLayer, METHOD_OVERRIDE_HEADER};
+pub use normalize::{PathNormalization, PathNormalizationLayer, PathNormalizationOptions};
+pub use not_found::{NotFoundRedirect, NotFoundRedirectLayer};
+pub use origin::{OriginWhitelist, OriginWhitelistLayer};
+pub use prefix::{OriginalPath, StripPrefix, StripPrefixLayer};
+pub use proxy_headers::{ProxyHeaders, ProxyHeadersLayer};
+pub use real_ip::{RealIp, RealIpLayer, RealIpService};
+pub use redirect::{HostRedirect, HostRedirectLayer, HostRedirectService};
+pub use response_limit::{ResponseSizeLimit, ResponseSizeLimitLayer};
+pub use rewrite::{PathRewrite, PathRewriteLayer, RewriteRule};
+pub use server_timing::{ServerTiming, ServerTimingLayer};
+pub use slow::{
+    warn_slow_request, SlowRequestCallback, SlowRequestDetector, SlowRequestDetectorLayer,
+};
+pub use tower_http::decompression::{RequestDecompression, RequestDecompressionLayer};
+pub use trace::{SpanContext, TraceContext, TraceContextLayer};
+
+mod audit;
+mod cache;
+mod cache_control;
+mod circuit_breaker;
+mod compression;
+mod cors;
+mod etag;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod method_not_allowed;
+mod method_override;
+mod normalize;
+mod not_found;
+mod origin;
+mod prefix;
+mod proxy_headers;
+mod real_ip;
+mod redirect;
+mod response_limit;
+mod rewrite;
+mod server_timing;
+mod slow;
+mod trace;
+
+pub(crate) use cors::cors_from_env;
+#[cfg(feature = "hot-reload")]
+pub(crate) use hot_reload::HotReloadLayer;
+pub(crate) use method_not_allowed::method_not_allowed;
+pub(crate) use not_found::SpaFallback;
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+
+/// whether `path` is `prefix` itself or under it, `prefix` should not end with `/`
+pub(crate) fn under_prefix(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Body read by [try_buffer_body]
+pub(crate) enum Buffered {
+    /// the whole body, not larger than `max_bytes`
+    Full(Bytes),
+    /// larger than `max_bytes`, `body` is the whole one with `read` and the rest chained back
+    TooLarge { read: Bytes, body: Body },
+}
+
+/// Buffer `body` up to `max_bytes`, without reading anything if it's known to be larger
+///
+/// A body found to be larger while reading is returned unchanged as [Buffered::TooLarge], so it
+/// can be passed through.
+pub(crate) async fn try_buffer_body(body: Body, max_bytes: usize) -> Result<Buffered, axum::Error> {
+    if body.size_hint().lower() > max_bytes as u64 {
+        return Ok(Buffered::TooLarge {
+            read: Bytes::new(),
+            body,
+        });
+    }
+
+    let mut buffer = Vec::new();
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk?);
+        if buffer.len() > max_bytes {
+            let read = Bytes::from(buffer);
+            let chained = futures_util::stream::once({
+                let read = read.clone();
+                async move { Ok(read) }
+            });
+            return Ok(Buffered::TooLarge {
+                read,
+                body: Body::from_stream(chained.chain(stream)),
+            });
+        }
+    }
+    Ok(Buffered::Full(Bytes::from(buffer)))
+}
+
+/// Buffer request `body` up to `max_bytes`, rejects larger ones by `413 Payload Too Large`
+pub(crate) async fn buffer_body(body: Body, max_bytes: usize) -> Result<Bytes, Response> {
+    match try_buffer_body(body, max_bytes).await {
+        Ok(Buffered::Full(body)) => Ok(body),
+        Ok(Buffered::TooLarge { .. }) => Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("request body is larger than {} bytes", max_bytes),
+        )
+            .into_response()),
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("read request body error: {}", e),
+        )
+            .into_response()),
+    }
+}
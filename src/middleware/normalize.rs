@@ -0,0 +1,175 @@
+use axum::{
+    extract::Request,
+    http::{header, uri::PathAndQuery, HeaderValue, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Which normalizations [PathNormalizationLayer] applies, all disabled by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PathNormalizationOptions {
+    /// redirect `/foo/` to `/foo`, `/` itself is kept
+    pub strip_trailing_slash: bool,
+    /// treat `/foo//bar` as `/foo/bar`
+    pub collapse_slashes: bool,
+    /// treat `/Foo` as `/foo`
+    pub lowercase_path: bool,
+}
+
+impl PathNormalizationOptions {
+    /// returns the normalized path and whether the client should be redirected to it
+    fn normalize(&self, path: &str) -> (String, bool) {
+        let mut normalized = if self.collapse_slashes {
+            let mut collapsed = String::with_capacity(path.len());
+            for c in path.chars() {
+                if !(c == '/' && collapsed.ends_with('/')) {
+                    collapsed.push(c);
+                }
+            }
+            collapsed
+        } else {
+            path.to_string()
+        };
+
+        if self.lowercase_path {
+            normalized = normalized.to_lowercase();
+        }
+
+        if self.strip_trailing_slash && normalized.len() > 1 && normalized.ends_with('/') {
+            // `//evil.com` in `Location` is a protocol-relative url to another host, browsers
+            // treat `\` as `/` too
+            let trimmed = normalized
+                .trim_end_matches('/')
+                .trim_start_matches(['/', '\\']);
+            return (format!("/{}", trimmed), true);
+        }
+
+        (normalized, false)
+    }
+}
+
+/// Layer which normalizes request path before routing
+///
+/// Collapsing slashes and lowercasing change the request uri in place. Stripping trailing
+/// slash responds `301 Moved Permanently` (`308 Permanent Redirect` for methods other than
+/// GET and HEAD, so the body is kept) to the normalized path, with query string preserved.
+/// It should wrap the whole router to affect routing.
+///
+/// This layer produces instances of the [PathNormalization] service.
+#[derive(Clone, Debug)]
+pub struct PathNormalizationLayer {
+    options: PathNormalizationOptions,
+}
+
+impl PathNormalizationLayer {
+    pub fn new(options: PathNormalizationOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl<S> Layer<S> for PathNormalizationLayer {
+    type Service = PathNormalization<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PathNormalization {
+            inner,
+            options: self.options,
+        }
+    }
+}
+
+/// Service which normalizes request path, see [PathNormalizationLayer]
+#[derive(Clone, Debug)]
+pub struct PathNormalization<S> {
+    inner: S,
+    options: PathNormalizationOptions,
+}
+
+impl<S> Service<Request> for PathNormalization<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        let uri = request.uri();
+        let (path, redirect) = self.options.normalize(uri.path());
+        if path != uri.path() {
+            let path_and_query = match uri.query() {
+                Some(query) => format!("{}?{}", path, query),
+                None => path,
+            };
+
+            if redirect {
+                if let Ok(location) = HeaderValue::from_str(&path_and_query) {
+                    let status = if matches!(*request.method(), Method::GET | Method::HEAD) {
+                        StatusCode::MOVED_PERMANENTLY
+                    } else {
+                        StatusCode::PERMANENT_REDIRECT
+                    };
+                    return Box::pin(async move {
+                        Ok((status, [(header::LOCATION, location)]).into_response())
+                    });
+                }
+            } else if let Ok(path_and_query) = PathAndQuery::try_from(path_and_query) {
+                let mut parts = uri.clone().into_parts();
+                parts.path_and_query = Some(path_and_query);
+                if let Ok(uri) = Uri::from_parts(parts) {
+                    *request.uri_mut() = uri;
+                }
+            }
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PathNormalizationOptions;
+
+    #[test]
+    fn test_normalize() {
+        let options = PathNormalizationOptions {
+            strip_trailing_slash: true,
+            collapse_slashes: true,
+            lowercase_path: true,
+        };
+        assert_eq!(
+            options.normalize("/Api//Users"),
+            ("/api/users".into(), false)
+        );
+        assert_eq!(
+            options.normalize("/api/users//"),
+            ("/api/users".into(), true)
+        );
+        assert_eq!(options.normalize("/"), ("/".into(), false));
+        assert_eq!(options.normalize("//"), ("/".into(), false));
+
+        let options = PathNormalizationOptions {
+            strip_trailing_slash: true,
+            ..Default::default()
+        };
+        assert_eq!(options.normalize("//evil.com/"), ("/evil.com".into(), true));
+        assert_eq!(
+            options.normalize("/\\evil.com/"),
+            ("/evil.com".into(), true)
+        );
+        assert_eq!(options.normalize("/a//b/"), ("/a//b".into(), true));
+
+        let options = PathNormalizationOptions::default();
+        assert_eq!(options.normalize("/Api//"), ("/Api//".into(), false));
+    }
+}
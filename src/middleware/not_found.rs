@@ -0,0 +1,94 @@
+use super::under_prefix;
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Response extension marks the response is the SPA `index.html` served as fallback
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SpaFallback;
+
+/// Layer which redirects not found requests under `prefix` to `url` with `302 Found`
+///
+/// Usually used to guide developers hitting wrong API endpoint to the API documentation.
+/// Besides `404 Not Found` responses, SPA `index.html` fallback of [SpaServer](crate::SpaServer)
+/// is also treated as not found.
+///
+/// This layer produces instances of the [NotFoundRedirect] service.
+#[derive(Clone, Debug)]
+pub struct NotFoundRedirectLayer {
+    prefix: Arc<str>,
+    location: HeaderValue,
+}
+
+impl NotFoundRedirectLayer {
+    /// # Panics
+    /// Panics if `url` is not a valid header value
+    pub fn new(prefix: &str, url: &str) -> Self {
+        Self {
+            prefix: prefix.trim_end_matches('/').into(),
+            location: HeaderValue::from_str(url).expect("invalid redirect url"),
+        }
+    }
+}
+
+impl<S> Layer<S> for NotFoundRedirectLayer {
+    type Service = NotFoundRedirect<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        NotFoundRedirect {
+            inner,
+            prefix: self.prefix.clone(),
+            location: self.location.clone(),
+        }
+    }
+}
+
+/// Service which redirects not found requests, see [NotFoundRedirectLayer]
+#[derive(Clone, Debug)]
+pub struct NotFoundRedirect<S> {
+    inner: S,
+    prefix: Arc<str>,
+    location: HeaderValue,
+}
+
+impl<S> Service<Request> for NotFoundRedirect<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if !under_prefix(request.uri().path(), &self.prefix) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let location = self.location.clone();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            if response.status() == StatusCode::NOT_FOUND
+                || response.extensions().get::<SpaFallback>().is_some()
+            {
+                return Ok((StatusCode::FOUND, [(header::LOCATION, location)]).into_response());
+            }
+
+            Ok(response)
+        })
+    }
+}
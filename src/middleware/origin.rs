@@ -0,0 +1,194 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Layer which rejects state-changing requests from origins not in the whitelist
+///
+/// `POST`, `PUT`, `DELETE` and `PATCH` requests with an `Origin` header not in the list get
+/// `403 Forbidden`, requests without `Origin` (non-browser clients) are let through.
+/// Preflight `OPTIONS` requests are answered directly, with CORS headers for allowed origins
+/// and `403 Forbidden` for others.
+///
+/// This layer produces instances of the [OriginWhitelist] service.
+#[derive(Clone, Debug)]
+pub struct OriginWhitelistLayer {
+    origins: Arc<Vec<String>>,
+}
+
+impl OriginWhitelistLayer {
+    /// `origins` are full origins such as `https://example.com`, trailing `/` is ignored
+    pub fn new(origins: Vec<String>) -> Self {
+        let origins = origins
+            .into_iter()
+            .map(|o| o.trim_end_matches('/').to_string())
+            .collect();
+        Self {
+            origins: Arc::new(origins),
+        }
+    }
+}
+
+impl<S> Layer<S> for OriginWhitelistLayer {
+    type Service = OriginWhitelist<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OriginWhitelist {
+            inner,
+            origins: self.origins.clone(),
+        }
+    }
+}
+
+/// Service which checks the `Origin` header, see [OriginWhitelistLayer]
+#[derive(Clone, Debug)]
+pub struct OriginWhitelist<S> {
+    inner: S,
+    origins: Arc<Vec<String>>,
+}
+
+impl<S> OriginWhitelist<S> {
+    fn allowed(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/');
+        self.origins.iter().any(|o| o.eq_ignore_ascii_case(origin))
+    }
+}
+
+fn is_state_changing(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::DELETE | Method::PATCH
+    )
+}
+
+fn preflight_response(origin: HeaderValue, headers: &HeaderMap) -> Response {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    response_headers.insert(header::VARY, HeaderValue::from_static("origin"));
+    for (request, allow) in [
+        (
+            header::ACCESS_CONTROL_REQUEST_METHOD,
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+        ),
+        (
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+        ),
+    ] {
+        if let Some(value) = headers.get(request) {
+            response_headers.insert(allow, value.clone());
+        }
+    }
+    response
+}
+
+impl<S> Service<Request> for OriginWhitelist<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let headers = request.headers();
+        let preflight = request.method() == Method::OPTIONS
+            && headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+        let origin = match headers.get(header::ORIGIN) {
+            Some(origin) if preflight || is_state_changing(request.method()) => origin,
+            _ => return Box::pin(self.inner.call(request)),
+        };
+
+        let allowed = origin.to_str().is_ok_and(|o| self.allowed(o));
+        let response = match (allowed, preflight) {
+            (false, _) => (StatusCode::FORBIDDEN, "origin not allowed").into_response(),
+            (true, true) => preflight_response(origin.clone(), headers),
+            (true, false) => return Box::pin(self.inner.call(request)),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OriginWhitelistLayer;
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+        routing::any,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_origin_whitelist() {
+        let app =
+            Router::new()
+                .route("/", any(|| async { "ok" }))
+                .layer(OriginWhitelistLayer::new(
+                    vec!["https://a.com/".to_string()],
+                ));
+        let status = |method: Method, origin: Option<&str>, preflight: bool| {
+            let mut request = Request::builder().method(method).uri("/");
+            if let Some(origin) = origin {
+                request = request.header(header::ORIGIN, origin);
+            }
+            if preflight {
+                request = request.header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST");
+            }
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                (
+                    response.status(),
+                    response
+                        .headers()
+                        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                        .cloned(),
+                )
+            }
+        };
+
+        assert_eq!(
+            status(Method::POST, Some("https://a.com"), false).await.0,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(Method::POST, Some("https://b.com"), false).await.0,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(status(Method::DELETE, None, false).await.0, StatusCode::OK);
+        assert_eq!(
+            status(Method::GET, Some("https://b.com"), false).await.0,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(Method::OPTIONS, Some("https://A.com"), true).await,
+            (
+                StatusCode::NO_CONTENT,
+                Some("https://A.com".parse().unwrap())
+            )
+        );
+        assert_eq!(
+            status(Method::OPTIONS, Some("https://b.com"), true).await.0,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
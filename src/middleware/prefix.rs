@@ -0,0 +1,95 @@
+use super::under_prefix;
+use axum::{
+    extract::Request,
+    http::{uri::PathAndQuery, Uri},
+};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Request path before stripped by [StripPrefixLayer], can access by Extension
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OriginalPath(pub PathBuf);
+
+/// Layer which strips `prefix` from request path, requests not under `prefix` are passed through
+///
+/// Note that it should wrap the whole router, layers added by [Router::layer](axum::Router::layer)
+/// run after routing, so changing uri in them does not affect routing.
+///
+/// This layer produces instances of the [StripPrefix] service.
+#[derive(Clone, Debug)]
+pub struct StripPrefixLayer {
+    prefix: Arc<str>,
+}
+
+impl StripPrefixLayer {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.trim_end_matches('/').into(),
+        }
+    }
+}
+
+impl<S> Layer<S> for StripPrefixLayer {
+    type Service = StripPrefix<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StripPrefix {
+            inner,
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+/// Service which strips path prefix, see [StripPrefixLayer]
+#[derive(Clone, Debug)]
+pub struct StripPrefix<S> {
+    inner: S,
+    prefix: Arc<str>,
+}
+
+impl<S> StripPrefix<S> {
+    fn strip(&self, uri: &Uri) -> Option<Uri> {
+        let path = uri.path();
+        if self.prefix.is_empty() || !under_prefix(path, &self.prefix) {
+            return None;
+        }
+
+        let stripped = &path[self.prefix.len()..];
+        let stripped = if stripped.is_empty() { "/" } else { stripped };
+        let path_and_query = match uri.query() {
+            Some(query) => format!("{}?{}", stripped, query),
+            None => stripped.to_string(),
+        };
+
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).ok()?);
+        Uri::from_parts(parts).ok()
+    }
+}
+
+impl<S> Service<Request> for StripPrefix<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        if let Some(uri) = self.strip(request.uri()) {
+            let original = OriginalPath(PathBuf::from(request.uri().path()));
+            request.extensions_mut().insert(original);
+            *request.uri_mut() = uri;
+        }
+
+        self.inner.call(request)
+    }
+}
@@ -0,0 +1,149 @@
+use axum::{
+    extract::Request,
+    http::{header::HeaderName, HeaderMap},
+    response::Response,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Layer which sanitizes headers of proxied requests and responses
+///
+/// Headers in [add_request](Self::add_request) are set into forwarded requests, such as
+/// `X-Forwarded-Proto`, and headers in [strip_response](Self::strip_response) are removed from
+/// upstream responses, such as `Server` or `Via`.
+///
+/// This layer produces instances of the [ProxyHeaders] service.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyHeadersLayer {
+    strip_response: Arc<Vec<HeaderName>>,
+    add_request: Arc<HeaderMap>,
+}
+
+impl ProxyHeadersLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// headers removed from responses
+    pub fn strip_response(mut self, headers: Vec<HeaderName>) -> Self {
+        self.strip_response = Arc::new(headers);
+        self
+    }
+
+    /// headers set into requests, existing ones are replaced
+    pub fn add_request(mut self, headers: HeaderMap) -> Self {
+        self.add_request = Arc::new(headers);
+        self
+    }
+
+    /// names of headers set by [add_request](Self::add_request)
+    #[cfg(feature = "ws-proxy")]
+    pub(crate) fn request_header_names(&self) -> impl Iterator<Item = &HeaderName> {
+        self.add_request.keys()
+    }
+}
+
+impl<S> Layer<S> for ProxyHeadersLayer {
+    type Service = ProxyHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ProxyHeaders {
+            inner,
+            strip_response: self.strip_response.clone(),
+            add_request: self.add_request.clone(),
+        }
+    }
+}
+
+/// Service which sanitizes proxy headers, see [ProxyHeadersLayer]
+#[derive(Clone, Debug)]
+pub struct ProxyHeaders<S> {
+    inner: S,
+    strip_response: Arc<Vec<HeaderName>>,
+    add_request: Arc<HeaderMap>,
+}
+
+impl<S> Service<Request> for ProxyHeaders<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        for name in self.add_request.keys() {
+            request.headers_mut().remove(name);
+            for value in self.add_request.get_all(name) {
+                request.headers_mut().append(name, value.clone());
+            }
+        }
+
+        let strip_response = self.strip_response.clone();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            for name in strip_response.iter() {
+                response.headers_mut().remove(name);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProxyHeadersLayer;
+    use axum::{
+        body::Body,
+        http::{header, HeaderMap, HeaderValue, Request},
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_proxy_headers() {
+        let mut add = HeaderMap::new();
+        add.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        let app = Router::new()
+            .route(
+                "/",
+                get(|headers: HeaderMap| async move {
+                    (
+                        [(header::SERVER, "upstream"), (header::VIA, "1.1 internal")],
+                        headers["x-forwarded-proto"].to_str().unwrap().to_string(),
+                    )
+                }),
+            )
+            .layer(
+                ProxyHeadersLayer::new()
+                    .strip_response(vec![header::SERVER])
+                    .add_request(add),
+            );
+
+        let request = Request::builder()
+            .uri("/")
+            .header("x-forwarded-proto", "http")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert!(!response.headers().contains_key(header::SERVER));
+        assert_eq!(response.headers()[header::VIA], "1.1 internal");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "https");
+    }
+}
@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request},
+    http::{header::HeaderName, request::Parts, Extensions, HeaderMap, StatusCode},
+    response::Response,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Client ip address set by [RealIpLayer], can be used as an extractor
+///
+/// If the layer is missing, or no ip found in the configured headers, the peer address
+/// of the connection is used. Extracting fails with `500 Internal Server Error` if none of them
+/// is available, use `Option<RealIp>` if it's expected.
+///
+/// Headers can be set by any client, so the headers read by [RealIpLayer] must only be trusted
+/// behind a reverse proxy which overwrites them, never use a [RealIp] read from them for
+/// security decisions otherwise.
+///
+/// # Example
+/// ```
+/// # use spa_rs::middleware::RealIp;
+/// async fn handler(RealIp(ip): RealIp) -> String {
+///     format!("your ip is {}", ip)
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RealIp(pub IpAddr);
+
+impl RealIp {
+    fn from_headers(headers: &HeaderMap, names: &[HeaderName]) -> Option<Self> {
+        names.iter().find_map(|name| {
+            // X-Forwarded-For is `client, proxy1, proxy2`, others usually contain a single ip
+            let value = headers.get(name)?.to_str().ok()?.split(',').next()?.trim();
+            value
+                .parse::<IpAddr>()
+                .or_else(|_| value.parse::<SocketAddr>().map(|a| a.ip()))
+                .ok()
+                .map(RealIp)
+        })
+    }
+
+    pub(crate) fn from_connect_info(extensions: &Extensions) -> Option<Self> {
+        extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| RealIp(addr.ip()))
+    }
+
+    /// the ip set by [RealIpLayer], or the peer address of the connection
+    pub(crate) fn from_extensions(extensions: &Extensions) -> Option<Self> {
+        extensions
+            .get::<RealIp>()
+            .copied()
+            .or_else(|| RealIp::from_connect_info(extensions))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RealIp
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        RealIp::from_extensions(&parts.extensions).ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can not determine client ip address",
+        ))
+    }
+}
+
+/// Layer which reads client ip from headers set by reverse proxies, and inserts it as [RealIp]
+/// request extension
+///
+/// Headers are tried in order, the peer address of the connection is used if none of them found.
+/// Only use it behind a reverse proxy which overwrites these headers, see [RealIp].
+///
+/// This layer produces instances of the [RealIpService] service.
+#[derive(Clone, Debug)]
+pub struct RealIpLayer {
+    headers: Arc<[HeaderName]>,
+}
+
+impl RealIpLayer {
+    pub fn new(headers: Vec<HeaderName>) -> Self {
+        Self {
+            headers: headers.into(),
+        }
+    }
+}
+
+impl Default for RealIpLayer {
+    /// Reads `X-Forwarded-For` only, which is set by most reverse proxies
+    fn default() -> Self {
+        Self::new(vec![HeaderName::from_static("x-forwarded-for")])
+    }
+}
+
+impl<S> Layer<S> for RealIpLayer {
+    type Service = RealIpService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RealIpService {
+            inner,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// Service which inserts [RealIp] extension, see [RealIpLayer]
+#[derive(Clone, Debug)]
+pub struct RealIpService<S> {
+    inner: S,
+    headers: Arc<[HeaderName]>,
+}
+
+impl<S> Service<Request> for RealIpService<S>
+where
+    S: Service<Request, Response = Response>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        let real_ip = RealIp::from_headers(request.headers(), &self.headers)
+            .or_else(|| RealIp::from_connect_info(request.extensions()));
+        if let Some(real_ip) = real_ip {
+            request.extensions_mut().insert(real_ip);
+        }
+
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RealIp;
+    use axum::http::{header::HeaderName, HeaderMap, HeaderValue};
+
+    #[test]
+    fn test_real_ip_from_headers() {
+        let cf = HeaderName::from_static("cf-connecting-ip");
+        let xff = HeaderName::from_static("x-forwarded-for");
+        let mut headers = HeaderMap::new();
+        headers.insert(&xff, HeaderValue::from_static("10.0.0.1, 10.0.0.2"));
+
+        let names = [cf.clone(), xff.clone()];
+        assert_eq!(
+            RealIp::from_headers(&headers, &names),
+            Some(RealIp([10, 0, 0, 1].into()))
+        );
+
+        headers.insert(&cf, HeaderValue::from_static("2001:db8::1"));
+        assert_eq!(
+            RealIp::from_headers(&headers, &names),
+            Some(RealIp("2001:db8::1".parse().unwrap()))
+        );
+
+        headers.insert(&cf, HeaderValue::from_static("not an ip"));
+        assert_eq!(RealIp::from_headers(&headers, &[cf]), None);
+    }
+}
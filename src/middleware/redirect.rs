@@ -0,0 +1,160 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::{
+    future::Future,
+    net::IpAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Which host is the canonical one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostRedirect {
+    /// redirect `www.example.com` to `example.com`
+    WwwToApex,
+    /// redirect `example.com` to `www.example.com`
+    ApexToWww,
+}
+
+impl HostRedirect {
+    /// returns the canonical host if `host` need to be redirected
+    ///
+    /// Port is preserved, ip address and single label host (such as `localhost`)
+    /// are never redirected.
+    pub fn redirect_host(&self, host: &str) -> Option<String> {
+        let (name, port) = match host.rsplit_once(':') {
+            // bare ipv6 address without port
+            Some((name, _)) if !name.ends_with(']') && name.contains(':') => (host, None),
+            Some((name, port)) => (name, Some(port)),
+            None => (host, None),
+        };
+        let name_no_brackets = name.trim_start_matches('[').trim_end_matches(']');
+        if name_no_brackets.parse::<IpAddr>().is_ok() || !name.contains('.') {
+            return None;
+        }
+
+        let new_name = match self {
+            HostRedirect::WwwToApex => name.strip_prefix("www.")?.to_string(),
+            HostRedirect::ApexToWww if name.starts_with("www.") => return None,
+            HostRedirect::ApexToWww => format!("www.{}", name),
+        };
+
+        Some(match port {
+            Some(port) => format!("{}:{}", new_name, port),
+            None => new_name,
+        })
+    }
+}
+
+/// Layer which redirect requests to the canonical host with `301 Moved Permanently`
+///
+/// This layer produces instances of the [HostRedirectService] service.
+#[derive(Clone, Debug)]
+pub struct HostRedirectLayer {
+    redirect: HostRedirect,
+    https: bool,
+}
+
+impl HostRedirectLayer {
+    /// `https` specific the scheme used in `Location` header
+    pub fn new(redirect: HostRedirect, https: bool) -> Self {
+        Self { redirect, https }
+    }
+}
+
+impl<S> Layer<S> for HostRedirectLayer {
+    type Service = HostRedirectService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HostRedirectService {
+            inner,
+            redirect: self.redirect,
+            https: self.https,
+        }
+    }
+}
+
+/// Service which redirect requests to the canonical host, see [HostRedirectLayer]
+#[derive(Clone, Debug)]
+pub struct HostRedirectService<S> {
+    inner: S,
+    redirect: HostRedirect,
+    https: bool,
+}
+
+impl<S> Service<Request> for HostRedirectService<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let host = request
+            .headers()
+            .get(header::HOST)
+            .and_then(|h| h.to_str().ok())
+            .or_else(|| request.uri().authority().map(|a| a.as_str()));
+        let location = host
+            .and_then(|h| self.redirect.redirect_host(h))
+            .and_then(|host| {
+                let scheme = if self.https { "https" } else { "http" };
+                let path = request
+                    .uri()
+                    .path_and_query()
+                    .map(|p| p.as_str())
+                    .unwrap_or("/");
+                HeaderValue::from_str(&format!("{}://{}{}", scheme, host, path)).ok()
+            });
+
+        match location {
+            Some(location) => Box::pin(async move {
+                Ok((
+                    StatusCode::MOVED_PERMANENTLY,
+                    [(header::LOCATION, location)],
+                )
+                    .into_response())
+            }),
+            None => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HostRedirect;
+
+    #[test]
+    fn test_redirect_host() {
+        let r = HostRedirect::WwwToApex;
+        assert_eq!(
+            r.redirect_host("www.example.com").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            r.redirect_host("www.example.com:8080").as_deref(),
+            Some("example.com:8080")
+        );
+        assert_eq!(r.redirect_host("example.com"), None);
+
+        let r = HostRedirect::ApexToWww;
+        assert_eq!(
+            r.redirect_host("example.com:8080").as_deref(),
+            Some("www.example.com:8080")
+        );
+        assert_eq!(r.redirect_host("www.example.com"), None);
+        assert_eq!(r.redirect_host("localhost:8080"), None);
+        assert_eq!(r.redirect_host("127.0.0.1:8080"), None);
+        assert_eq!(r.redirect_host("[::1]:8080"), None);
+    }
+}
@@ -0,0 +1,152 @@
+use super::under_prefix;
+use axum::{
+    body::{Body, HttpBody},
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use log::error;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Layer which limits response body size, for catching routes leaking more data than expected
+///
+/// Responses known to be larger than the limit are replaced with `500 Internal Server Error`,
+/// streaming responses are aborted once the limit is exceeded, since status is already sent.
+/// When several paths match, the limit of the longest one applies.
+///
+/// This layer produces instances of the [ResponseSizeLimit] service.
+///
+/// # Example
+/// ```
+/// # use spa_rs::middleware::ResponseSizeLimitLayer;
+/// let layer = ResponseSizeLimitLayer::new(10 * 1024 * 1024).for_path("/api/profile", 1024);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ResponseSizeLimitLayer {
+    limits: Arc<Vec<(String, usize)>>,
+}
+
+impl ResponseSizeLimitLayer {
+    /// limit all responses to `max_bytes`
+    pub fn new(max_bytes: usize) -> Self {
+        Self::default().for_path("/", max_bytes)
+    }
+
+    /// limit responses of `path` and paths under it to `max_bytes`
+    pub fn for_path(mut self, path: &str, max_bytes: usize) -> Self {
+        Arc::make_mut(&mut self.limits).push((path.trim_end_matches('/').to_string(), max_bytes));
+        self
+    }
+
+    fn limit(&self, path: &str) -> Option<usize> {
+        self.limits
+            .iter()
+            .filter(|(prefix, _)| under_prefix(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, max)| *max)
+    }
+}
+
+impl<S> Layer<S> for ResponseSizeLimitLayer {
+    type Service = ResponseSizeLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseSizeLimit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service which limits response body size, see [ResponseSizeLimitLayer]
+#[derive(Clone, Debug)]
+pub struct ResponseSizeLimit<S> {
+    inner: S,
+    layer: ResponseSizeLimitLayer,
+}
+
+fn limit_body(response: Response, max: usize, path: String) -> Response {
+    let size = response.body().size_hint();
+    if size.upper().is_some_and(|upper| upper <= max as u64) {
+        return response;
+    }
+    if size.lower() > max as u64 {
+        error!(
+            "response of {} is {} bytes, exceeds limit {}",
+            path,
+            size.lower(),
+            max
+        );
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    let (parts, body) = response.into_parts();
+    let mut sent = 0;
+    let stream = body.into_data_stream().map(move |chunk| {
+        let chunk = chunk?;
+        sent += chunk.len();
+        if sent > max {
+            error!("response of {} exceeds limit {}, aborted", path, max);
+            return Err(axum::Error::new(format!(
+                "response body exceeds limit {}",
+                max
+            )));
+        }
+        Ok(chunk)
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+impl<S> Service<Request> for ResponseSizeLimit<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let path = request.uri().path().to_string();
+        let limit = self.layer.limit(&path);
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            Ok(match limit {
+                Some(max) => limit_body(response, max, path),
+                None => response,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ResponseSizeLimitLayer;
+
+    #[test]
+    fn test_limit() {
+        let layer = ResponseSizeLimitLayer::default()
+            .for_path("/api", 100)
+            .for_path("/api/profile/", 10);
+        assert_eq!(layer.limit("/api/profile/1"), Some(10));
+        assert_eq!(layer.limit("/api/files"), Some(100));
+        assert_eq!(layer.limit("/apis"), None);
+        assert_eq!(layer.limit("/"), None);
+        assert_eq!(
+            ResponseSizeLimitLayer::new(1).for_path("/a", 2).limit("/b"),
+            Some(1)
+        );
+    }
+}
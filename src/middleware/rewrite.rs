@@ -0,0 +1,129 @@
+use axum::{
+    extract::Request,
+    http::{uri::PathAndQuery, Uri},
+};
+use regex::Regex;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// A rule of [PathRewriteLayer]
+///
+/// `replacement` can refer to capture groups of `pattern` by `$1`, `$name`, etc.
+/// See [Regex::replace] for the syntax.
+#[derive(Clone, Debug)]
+pub struct RewriteRule {
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+impl RewriteRule {
+    /// # Panics
+    /// Panics if `pattern` is not a valid regex
+    pub fn new(pattern: &str, replacement: impl Into<String>) -> Self {
+        Self {
+            pattern: Regex::new(pattern)
+                .unwrap_or_else(|e| panic!("invalid rewrite pattern {:?}: {}", pattern, e)),
+            replacement: replacement.into(),
+        }
+    }
+
+    fn rewrite(&self, path: &str) -> Option<String> {
+        self.pattern
+            .is_match(path)
+            .then(|| self.pattern.replace(path, &self.replacement).into_owned())
+    }
+}
+
+/// Layer which rewrites request path by rules, the first matched rule wins
+///
+/// Query string is preserved. Like [StripPrefixLayer](super::StripPrefixLayer), it should wrap
+/// the whole router to affect routing.
+///
+/// This layer produces instances of the [PathRewrite] service.
+///
+/// # Example
+/// ```
+/// # use spa_rs::middleware::{PathRewriteLayer, RewriteRule};
+/// let layer = PathRewriteLayer::new(vec![RewriteRule::new("^/v1/(.*)$", "/api/$1")]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathRewriteLayer {
+    rules: Arc<[RewriteRule]>,
+}
+
+impl PathRewriteLayer {
+    pub fn new(rules: Vec<RewriteRule>) -> Self {
+        Self {
+            rules: rules.into(),
+        }
+    }
+}
+
+impl<S> Layer<S> for PathRewriteLayer {
+    type Service = PathRewrite<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PathRewrite {
+            inner,
+            rules: self.rules.clone(),
+        }
+    }
+}
+
+/// Service which rewrites request path, see [PathRewriteLayer]
+#[derive(Clone, Debug)]
+pub struct PathRewrite<S> {
+    inner: S,
+    rules: Arc<[RewriteRule]>,
+}
+
+impl<S> PathRewrite<S> {
+    fn rewrite(&self, uri: &Uri) -> Option<Uri> {
+        let path = self.rules.iter().find_map(|r| r.rewrite(uri.path()))?;
+        let path_and_query = match uri.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        };
+
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).ok()?);
+        Uri::from_parts(parts).ok()
+    }
+}
+
+impl<S> Service<Request> for PathRewrite<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        if let Some(uri) = self.rewrite(request.uri()) {
+            log::debug!("rewrite {} to {}", request.uri(), uri);
+            *request.uri_mut() = uri;
+        }
+
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RewriteRule;
+
+    #[test]
+    fn test_rewrite_rule() {
+        let rule = RewriteRule::new("^/v1/(?P<rest>.*)$", "/api/$rest");
+        assert_eq!(rule.rewrite("/v1/users/1").as_deref(), Some("/api/users/1"));
+        assert_eq!(rule.rewrite("/v2/users"), None);
+    }
+}
@@ -0,0 +1,70 @@
+use axum::{
+    extract::Request,
+    http::{header::HeaderName, HeaderValue},
+    response::Response,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+
+const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+/// Layer which sets `Server-Timing: total;dur=<ms>` on every response
+///
+/// Elapsed time is measured until the response head is ready, streaming body is not
+/// counted. `Server-Timing` set by handlers is overwritten.
+///
+/// This layer produces instances of the [ServerTiming] service.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServerTimingLayer;
+
+impl ServerTimingLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for ServerTimingLayer {
+    type Service = ServerTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ServerTiming { inner }
+    }
+}
+
+/// Service which sets `Server-Timing` header, see [ServerTimingLayer]
+#[derive(Clone, Debug)]
+pub struct ServerTiming<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for ServerTiming<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let start = Instant::now();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+            if let Ok(value) = HeaderValue::from_str(&format!("total;dur={:.3}", elapsed)) {
+                response.headers_mut().insert(SERVER_TIMING, value);
+            }
+            Ok(response)
+        })
+    }
+}
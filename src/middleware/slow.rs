@@ -0,0 +1,99 @@
+use axum::{
+    extract::Request,
+    http::{Method, Uri},
+};
+use log::warn;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+
+/// Callback of [SlowRequestDetectorLayer], called with method, uri and elapsed time of slow requests
+pub type SlowRequestCallback = fn(&Method, &Uri, Duration);
+
+/// Default callback of [SlowRequestDetectorLayer], log slow requests with `warn!`
+pub fn warn_slow_request(method: &Method, uri: &Uri, elapsed: Duration) {
+    warn!("slow request {} {} took {:?}", method, uri, elapsed);
+}
+
+/// Layer which calls `callback` when a request takes longer than `threshold`
+///
+/// Elapsed time is measured until the response head is ready, streaming body is not
+/// counted. The response is returned to client as usual.
+///
+/// This layer produces instances of the [SlowRequestDetector] service.
+///
+/// # Example
+/// ```
+/// # use spa_rs::middleware::{warn_slow_request, SlowRequestDetectorLayer};
+/// # use std::time::Duration;
+/// let layer = SlowRequestDetectorLayer::new(Duration::from_secs(1), warn_slow_request);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SlowRequestDetectorLayer {
+    threshold: Duration,
+    callback: SlowRequestCallback,
+}
+
+impl SlowRequestDetectorLayer {
+    pub fn new(threshold: Duration, callback: SlowRequestCallback) -> Self {
+        Self {
+            threshold,
+            callback,
+        }
+    }
+}
+
+impl<S> Layer<S> for SlowRequestDetectorLayer {
+    type Service = SlowRequestDetector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowRequestDetector {
+            inner,
+            threshold: self.threshold,
+            callback: self.callback,
+        }
+    }
+}
+
+/// Service which detects slow requests, see [SlowRequestDetectorLayer]
+#[derive(Clone, Debug)]
+pub struct SlowRequestDetector<S> {
+    inner: S,
+    threshold: Duration,
+    callback: SlowRequestCallback,
+}
+
+impl<S> Service<Request> for SlowRequestDetector<S>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let method = request.method().clone();
+        let uri = request.uri().clone();
+        let threshold = self.threshold;
+        let callback = self.callback;
+        let start = Instant::now();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await;
+            let elapsed = start.elapsed();
+            if elapsed > threshold {
+                callback(&method, &uri, elapsed);
+            }
+            response
+        })
+    }
+}
@@ -0,0 +1,220 @@
+use axum::{extract::Request, http::HeaderValue, response::Response};
+use rand::{thread_rng, Rng};
+use std::{
+    fmt::Write,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::Instrument;
+
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+
+/// [W3C Trace Context](https://www.w3.org/TR/trace-context/) of current request
+///
+/// It is inserted into request extensions by [TraceContextLayer], so can be
+/// accessed by `Extension<SpanContext>` in handler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpanContext {
+    /// 16 bytes trace id in lower hex, shared by the whole trace
+    pub trace_id: String,
+    /// span id of the caller, `None` if current request starts a new trace
+    pub parent_id: Option<String>,
+    /// 8 bytes span id of current request in lower hex
+    pub span_id: String,
+    /// trace flags, `0x01` means sampled
+    pub flags: u8,
+    /// vendor specific `tracestate` header, propagated as is
+    pub trace_state: Option<String>,
+}
+
+impl SpanContext {
+    /// continue the trace from `traceparent` header, returns `None` if header is invalid
+    pub fn from_traceparent(traceparent: &str, trace_state: Option<String>) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_id = parts.next()?;
+        let flags = parts.next()?;
+        if version.len() != 2
+            || version == "ff"
+            || !is_hex(version)
+            || (version == "00" && parts.next().is_some())
+        {
+            return None;
+        }
+
+        if trace_id.len() != 32 || !is_hex(trace_id) || trace_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+
+        if parent_id.len() != 16 || !is_hex(parent_id) || parent_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+
+        if flags.len() != 2 {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: trace_id.to_owned(),
+            parent_id: Some(parent_id.to_owned()),
+            span_id: rand_hex(8),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            trace_state,
+        })
+    }
+
+    /// start a new sampled trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: rand_hex(16),
+            parent_id: None,
+            span_id: rand_hex(8),
+            flags: 0x01,
+            trace_state: None,
+        }
+    }
+
+    /// `traceparent` header value which take current span as parent
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn rand_hex(bytes: usize) -> String {
+    let mut rng = thread_rng();
+    let mut result = String::with_capacity(bytes * 2);
+    loop {
+        result.clear();
+        for _ in 0..bytes {
+            let _ = write!(result, "{:02x}", rng.gen::<u8>());
+        }
+
+        // all zero id is invalid
+        if result.bytes().any(|b| b != b'0') {
+            return result;
+        }
+    }
+}
+
+/// Layer which propagate W3C Trace Context headers
+///
+/// It reads `traceparent` and `tracestate` from request, continues the trace (or starts
+/// a new one) in a [tracing] span, and writes `traceparent` back to response.
+///
+/// This layer produces instances of the [TraceContext] service.
+#[derive(Clone, Debug, Default)]
+pub struct TraceContextLayer;
+
+impl TraceContextLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for TraceContextLayer {
+    type Service = TraceContext<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceContext { inner }
+    }
+}
+
+/// Service which propagate W3C Trace Context headers, see [TraceContextLayer]
+#[derive(Clone, Debug)]
+pub struct TraceContext<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for TraceContext<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        let headers = request.headers();
+        let trace_state = headers
+            .get(TRACESTATE)
+            .and_then(|v| v.to_str().ok())
+            .map(ToOwned::to_owned);
+        let span_context = headers
+            .get(TRACEPARENT)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| SpanContext::from_traceparent(v, trace_state))
+            .unwrap_or_else(SpanContext::new_root);
+
+        let span = tracing::info_span!(
+            "request",
+            trace_id = %span_context.trace_id,
+            span_id = %span_context.span_id,
+            parent_id = span_context.parent_id.as_deref().unwrap_or_default(),
+            method = %request.method(),
+            uri = %request.uri(),
+        );
+
+        request.extensions_mut().insert(span_context.clone());
+        let future = self.inner.call(request);
+        Box::pin(
+            async move {
+                let mut response = future.await?;
+                let headers = response.headers_mut();
+                if let Ok(v) = HeaderValue::from_str(&span_context.traceparent()) {
+                    headers.insert(TRACEPARENT, v);
+                }
+
+                if let Some(v) = span_context
+                    .trace_state
+                    .and_then(|v| HeaderValue::from_str(&v).ok())
+                {
+                    headers.insert(TRACESTATE, v);
+                }
+
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SpanContext;
+
+    #[test]
+    fn test_traceparent() {
+        let ctx = SpanContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            None,
+        )
+        .unwrap();
+        assert_eq!(ctx.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_eq!(ctx.flags, 1);
+        assert_ne!(ctx.span_id, "00f067aa0ba902b7");
+        assert!(ctx
+            .traceparent()
+            .starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+
+        assert!(SpanContext::from_traceparent(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        assert!(SpanContext::from_traceparent("00-abc-def-01", None).is_none());
+    }
+}
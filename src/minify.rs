@@ -0,0 +1,71 @@
+//! Collapsing whitespace of html files, see
+//! [SpaServer::compress_embedded_html](crate::SpaServer::compress_embedded_html)
+//!
+//! It's a simple state machine instead of a html parser, so only whitespace is touched.
+
+/// Tags whose content is kept as is
+const RAW_TAGS: &[&str] = &["pre", "script", "style", "textarea"];
+
+/// Collapse consecutive whitespace into a single space, except in [RAW_TAGS]
+pub(crate) fn collapse_whitespace(html: &str) -> String {
+    // ascii lowercase keeps byte offsets, so it can be indexed along with `html`
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    let mut in_space = false;
+    while let Some(c) = html[pos..].chars().next() {
+        if c == '<' {
+            let rest = &lower[pos + 1..];
+            if let Some(tag) = RAW_TAGS.iter().find(|t| is_open_tag(rest, t)) {
+                // copy until the close tag, which is handled as normal text then
+                let end = lower[pos..]
+                    .find(&format!("</{}", tag))
+                    .map_or(html.len(), |i| pos + i);
+                out.push_str(&html[pos..end]);
+                pos = end;
+                in_space = false;
+                continue;
+            }
+        }
+
+        if c.is_ascii_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+        pos += c.len_utf8();
+    }
+
+    out
+}
+
+fn is_open_tag(s: &str, tag: &str) -> bool {
+    s.strip_prefix(tag)
+        .is_some_and(|r| r.starts_with(|c: char| c == '>' || c.is_ascii_whitespace()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::collapse_whitespace;
+
+    #[test]
+    fn test_collapse_whitespace() {
+        assert_eq!(
+            collapse_whitespace("<div>\n    <p>a  b</p>\n</div>\n"),
+            "<div> <p>a b</p> </div> "
+        );
+        assert_eq!(
+            collapse_whitespace("<PRE>  a\n  b</PRE>  <script>\n  x  = 1;\n</script>"),
+            "<PRE>  a\n  b</PRE> <script>\n  x  = 1;\n</script>"
+        );
+        assert_eq!(
+            collapse_whitespace("<preview>  a</preview>\u{a0}\u{a0}"),
+            "<preview> a</preview>\u{a0}\u{a0}"
+        );
+        assert_eq!(collapse_whitespace("<style> a  {}"), "<style> a  {}");
+    }
+}
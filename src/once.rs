@@ -0,0 +1,47 @@
+//! Serving exactly one connection, see [SpaServer::run_once](crate::SpaServer::run_once)
+use axum_server::{accept::Accept, Handle};
+use futures_util::future::{ready, Ready};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Acceptor which accepts the first connection and shutdown the server gracefully, so the
+/// server returns after the connection finished
+///
+/// Connections accepted by the OS before shutdown are dropped.
+#[derive(Clone)]
+pub(crate) struct OnceAcceptor {
+    accepted: Arc<AtomicBool>,
+    handle: Handle,
+}
+
+impl OnceAcceptor {
+    pub(crate) fn new(handle: Handle) -> Self {
+        Self {
+            accepted: Arc::new(AtomicBool::new(false)),
+            handle,
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for OnceAcceptor {
+    type Stream = I;
+    type Service = S;
+    type Future = Ready<io::Result<(I, S)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        if self.accepted.swap(true, Ordering::SeqCst) {
+            return ready(Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "only one connection is served",
+            )));
+        }
+
+        self.handle.graceful_shutdown(None);
+        ready(Ok((stream, service)))
+    }
+}
@@ -0,0 +1,128 @@
+//! Options of [SpaServer](crate::SpaServer), grouped by the part of the server they configure
+//!
+//! None of them depend on the server data, so they are moved as a whole by
+//! [SpaServer::map_data](crate::SpaServer::map_data).
+use crate::{
+    health::HealthCheck, introspection, middleware, ErrorHandler, RouterLayer, RouterLayerFactory,
+    SocketOptions, DEFAULT_HEALTH_CHECK_TIMEOUT,
+};
+use axum::{routing::MethodRouter, Router};
+use axum_server::Handle;
+use std::{collections::HashMap, net::Ipv6Addr, path::PathBuf, time::Duration};
+
+/// Layers applied when building the router
+#[derive(Default)]
+pub(crate) struct LayerOptions {
+    pub(crate) factories: Vec<RouterLayerFactory>,
+    pub(crate) before_auth: Vec<RouterLayer>,
+    pub(crate) after_auth: Vec<Box<dyn Fn(Router) -> Router + Send>>,
+    pub(crate) auth_routes: Vec<(String, Router, RouterLayer)>,
+}
+
+/// How requests are routed, rewritten or redirected
+#[derive(Default)]
+pub(crate) struct RoutingOptions {
+    pub(crate) host_redirect: Option<middleware::HostRedirect>,
+    pub(crate) not_found_redirects: Vec<(String, String)>,
+    pub(crate) version_path: Option<String>,
+    pub(crate) strip_prefix: Option<String>,
+    pub(crate) rewrite_rules: Vec<middleware::RewriteRule>,
+    pub(crate) path_normalization: Option<middleware::PathNormalizationOptions>,
+    pub(crate) spa_fallback_condition: Option<fn(&axum::extract::Request) -> bool>,
+    pub(crate) base_path: Option<String>,
+    pub(crate) api_root: Option<MethodRouter>,
+    pub(crate) fallback: Option<MethodRouter>,
+    pub(crate) route_conflict_detection: bool,
+    pub(crate) routes: Vec<introspection::RouteInfo>,
+    #[cfg(any(feature = "reverse-proxy", feature = "ws-proxy"))]
+    pub(crate) proxy_headers: middleware::ProxyHeadersLayer,
+    #[cfg(feature = "ws-proxy")]
+    pub(crate) ws_proxies: crate::ws_proxy::WsProxies,
+    #[cfg(feature = "swagger-ui")]
+    pub(crate) swagger_ui: Option<crate::swagger::SwaggerUi>,
+    #[cfg(feature = "swagger-ui")]
+    pub(crate) openapi_spec: Option<serde_json::Value>,
+    #[cfg(feature = "debug")]
+    pub(crate) introspection_path: Option<String>,
+}
+
+/// How the spa root and static paths are served
+#[derive(Default)]
+pub(crate) struct StaticOptions {
+    pub(crate) spa_index_file: Option<String>,
+    pub(crate) max_age: HashMap<String, u32>,
+    pub(crate) path_envs: Vec<(String, String)>,
+    pub(crate) etag_strategy: Option<middleware::EtagStrategy>,
+    pub(crate) compress_html: bool,
+    #[cfg(feature = "brotli")]
+    pub(crate) precompressed_br: Option<u32>,
+    #[cfg(feature = "hot-reload")]
+    pub(crate) hot_reload_paths: Vec<(String, PathBuf)>,
+    #[cfg(feature = "hot-reload")]
+    pub(crate) live_reload_path: Option<String>,
+}
+
+/// Request and response handling shared by all routes
+#[derive(Default)]
+pub(crate) struct HttpOptions {
+    pub(crate) error_handler: Option<ErrorHandler>,
+    pub(crate) real_ip_headers: Vec<String>,
+    pub(crate) cors_from_env: bool,
+    pub(crate) server_timing: bool,
+    pub(crate) response_size_limits: Vec<(String, usize)>,
+    pub(crate) request_id_header: Option<String>,
+}
+
+/// Health endpoints, see [SpaServer::health_check_with_dependencies](crate::SpaServer::health_check_with_dependencies)
+pub(crate) struct HealthOptions {
+    pub(crate) checks: Vec<(String, Vec<HealthCheck>)>,
+    pub(crate) timeout: Duration,
+}
+
+impl Default for HealthOptions {
+    fn default() -> Self {
+        Self {
+            checks: Vec::new(),
+            timeout: DEFAULT_HEALTH_CHECK_TIMEOUT,
+        }
+    }
+}
+
+/// How the server listens, serves and shuts down, still needed after the router is built
+#[derive(Default)]
+pub(crate) struct ServeOptions {
+    pub(crate) ipv6: Option<Ipv6Addr>,
+    pub(crate) socket_options: SocketOptions,
+    pub(crate) handle: Handle,
+    pub(crate) serve_once: bool,
+    pub(crate) pid_file: Option<PathBuf>,
+    pub(crate) drain_on_shutdown: bool,
+    pub(crate) shutdown_timeout: Option<Duration>,
+    pub(crate) acme_challenge_dir: Option<PathBuf>,
+    #[cfg(feature = "acme")]
+    pub(crate) auto_cert: Option<crate::acme::AutoCert>,
+    #[cfg(feature = "rustls")]
+    pub(crate) tls: TlsOptions,
+}
+
+/// Rustls options beyond the certificate, see [SpaServer::client_ca](crate::SpaServer::client_ca)
+#[cfg(feature = "rustls")]
+pub(crate) struct TlsOptions {
+    pub(crate) client_ca: Option<Vec<u8>>,
+    pub(crate) alpn_protocols: Vec<Vec<u8>>,
+    pub(crate) sni_certs: Vec<(String, crate::HttpsConfig)>,
+}
+
+#[cfg(feature = "rustls")]
+impl Default for TlsOptions {
+    fn default() -> Self {
+        Self {
+            client_ca: None,
+            alpn_protocols: crate::tls::DEFAULT_ALPN_PROTOCOLS
+                .iter()
+                .map(|p| p.to_vec())
+                .collect(),
+            sni_certs: Vec::new(),
+        }
+    }
+}
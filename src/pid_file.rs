@@ -0,0 +1,61 @@
+//! Pid file for process managers, see [SpaServer::pid_file](crate::SpaServer::pid_file)
+use anyhow::{bail, Context, Result};
+use log::warn;
+use std::{fs, path::PathBuf};
+
+/// Pid file written when created, and removed when dropped
+pub(crate) struct PidFile(PathBuf);
+
+impl PidFile {
+    /// write pid of current process into `path`
+    ///
+    /// Returns error if `path` contains pid of another alive process, stale pid is overwritten.
+    pub(crate) fn create(path: PathBuf) -> Result<Self> {
+        if let Some(pid) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|p| p.trim().parse::<u32>().ok())
+        {
+            if pid != std::process::id() && is_alive(pid) {
+                bail!(
+                    "pid file {} exists, another instance is running with pid {}",
+                    path.display(),
+                    pid
+                );
+            }
+            warn!("overwrite stale pid file {}", path.display());
+        }
+
+        fs::write(&path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("write pid file {} error", path.display()))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.0) {
+            warn!("remove pid file {} error: {}", self.0.display(), e);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_alive(pid: u32) -> bool {
+    PathBuf::from(format!("/proc/{}", pid)).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // signal 0 only checks the existence, EPERM means it exists but owned by other user
+    unsafe { libc::kill(pid, 0) == 0 }
+    || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    warn!("can not check pid is alive on this platform, treated as stale");
+    false
+}
@@ -0,0 +1,51 @@
+//! Reloading config file in runtime, see [SpaServer::run_with_config_reload](crate::SpaServer::run_with_config_reload)
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// How often the config file is checked
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn load<Cfg: DeserializeOwned>(path: &Path) -> Result<Cfg> {
+    let content = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_slice(&content).with_context(|| format!("parse {}", path.display()))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Apply config once at first, then apply again whenever mtime of `path` changed
+///
+/// Invalid config is logged and ignored, so the last valid one keeps working.
+pub(crate) async fn watch<T, Cfg, F>(path: PathBuf, data: T, apply: F) -> Result<()>
+where
+    Cfg: DeserializeOwned,
+    F: Fn(&T, Cfg),
+{
+    let mut last_modified = modified(&path);
+    apply(&data, load(&path)?);
+
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let current = modified(&path);
+        if current == last_modified {
+            continue;
+        }
+
+        last_modified = current;
+        match load(&path) {
+            Ok(config) => {
+                info!("config {} reloaded", path.display());
+                apply(&data, config);
+            }
+            Err(e) => warn!("config reload failed, keep using the old one: {:?}", e),
+        }
+    }
+}
@@ -1,10 +1,74 @@
 //! A tower middleware who can reading and writing session data from Cookie.
 //!
 use crate::filter::Predicate;
-use axum::{extract::Request, http::StatusCode, response::Response};
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use headers::{Cookie, HeaderMapExt};
-use parking_lot::RwLock;
-use std::{cmp::PartialEq, collections::HashMap, sync::Arc};
+use hmac::{Hmac, Mac};
+use lru::LruCache;
+use parking_lot::{Mutex, MutexGuard};
+use sha2::Sha256;
+use std::{cmp::PartialEq, collections::HashMap, num::NonZeroUsize, sync::Arc};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Secrets to sign session cookie values with HMAC-SHA256, can access by Extension
+/// after [AddSession::secret] or [SpaServer::cookie_secret](crate::SpaServer::cookie_secret)
+/// is set.
+///
+/// Signed value is `<value>|<hmac>`, [RequireSession] rejects cookies with invalid signature.
+/// The first secret is used for signing, the rest are only used for verifying, so secret
+/// can be rotated without logging out all users.
+#[derive(Clone, Debug)]
+pub struct CookieSecret {
+    secrets: Arc<[Vec<u8>]>,
+}
+
+impl CookieSecret {
+    /// return new CookieSecret with one secret
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            secrets: vec![secret.to_vec()].into(),
+        }
+    }
+
+    /// return new CookieSecret with the current secret and legacy ones
+    ///
+    /// # Panics
+    /// Panics if `secrets` is empty
+    pub fn with_rotation(secrets: Vec<[u8; 32]>) -> Self {
+        assert!(!secrets.is_empty(), "at least one cookie secret is needed");
+        Self {
+            secrets: secrets.iter().map(|s| s.to_vec()).collect(),
+        }
+    }
+
+    /// sign `value` with the current secret, returns the value should be set in cookie
+    pub fn sign(&self, value: &str) -> String {
+        let signature = Self::mac(&self.secrets[0], value).finalize().into_bytes();
+        format!("{}|{}", value, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// verify the signed cookie value with all secrets, returns the original value if valid
+    pub fn verify<'a>(&self, signed: &'a str) -> Option<&'a str> {
+        let (value, signature) = signed.rsplit_once('|')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.secrets
+            .iter()
+            .any(|secret| Self::mac(secret, value).verify_slice(&signature).is_ok())
+            .then_some(value)
+    }
+
+    fn mac(secret: &[u8], value: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC can take key of any size");
+        mac.update(value.as_bytes());
+        mac
+    }
+}
 
 /// Session object, can access by Extension in RequireSession layer.
 ///
@@ -17,13 +81,25 @@ pub struct Session<T> {
     pub all: Arc<SessionStore<T>>,
 }
 
+impl<T: Clone + PartialEq> Session<T> {
+    /// insert a copy of current session data under `new_key`, and return the new key
+    ///
+    /// It's useful when admin need to impersonate a user, the returned key can be set
+    /// as a new cookie, so admin and user will not share the same session key.
+    pub fn fork(&self, new_key: impl Into<String>) -> String {
+        let new_key = new_key.into();
+        self.all.insert(new_key.clone(), self.current.clone());
+        new_key
+    }
+}
+
 /// Session storage, can access by Extersion in AddSession layer.
 ///
 /// See [AddSession] example for usage
 #[derive(Debug)]
 pub struct SessionStore<T> {
     key: String,
-    inner: RwLock<HashMap<String, T>>,
+    inner: Mutex<LruCache<String, T>>,
 }
 
 impl<T: PartialEq> SessionStore<T> {
@@ -31,7 +107,20 @@ impl<T: PartialEq> SessionStore<T> {
     pub fn new(key: impl Into<String>) -> Self {
         SessionStore {
             key: key.into(),
-            inner: RwLock::new(HashMap::new()),
+            inner: Mutex::new(LruCache::unbounded()),
+        }
+    }
+
+    /// return new SessionStore with specific key, which holds at most `max` sessions
+    ///
+    /// When inserting a new session into a full store, the least recently used one is evicted.
+    /// `max` less than 1 is treated as 1.
+    pub fn with_capacity(key: impl Into<String>, max: usize) -> Self {
+        SessionStore {
+            key: key.into(),
+            inner: Mutex::new(LruCache::new(
+                NonZeroUsize::new(max).unwrap_or(NonZeroUsize::MIN),
+            )),
         }
     }
 
@@ -42,49 +131,262 @@ impl<T: PartialEq> SessionStore<T> {
 
     /// insert a new session item
     pub fn insert(&self, k: impl Into<String>, v: T) {
-        self.inner.write().insert(k.into(), v);
+        self.inner.lock().put(k.into(), v);
     }
 
     /// remove the session item
     pub fn remove(&self, v: T) {
-        self.inner.write().retain(|_, x| *x != v);
+        self.remove_all_by(|x| *x == v);
+    }
+}
+
+impl<T> SessionStore<T> {
+    /// keys of all sessions whose value matches `f`, such as all sessions of a user
+    ///
+    /// It's a `O(n)` scan over all sessions with the store locked, recently used order is not updated.
+    pub fn lookup_all_by<F: Fn(&T) -> bool>(&self, f: F) -> Vec<String> {
+        self.inner
+            .lock()
+            .iter()
+            .filter(|(_, v)| f(v))
+            .map(|(k, _)| k.clone())
+            .collect()
+    }
+
+    /// remove all sessions whose value matches `f` and return the count removed, such as
+    /// logging out a user from all devices
+    ///
+    /// It's a `O(n)` scan over all sessions with the store locked.
+    pub fn remove_all_by<F: Fn(&T) -> bool>(&self, f: F) -> usize {
+        let mut inner = self.inner.lock();
+        let keys: Vec<String> = inner
+            .iter()
+            .filter(|(_, v)| f(v))
+            .map(|(k, _)| k.clone())
+            .collect();
+        for k in &keys {
+            inner.pop(k);
+        }
+        keys.len()
+    }
+
+    /// iterate all sessions without cloning, recently used order is not updated
+    ///
+    /// The store is locked until the returned [SessionIter] is dropped, so don't hold it
+    /// for long, and it can not be held across `.await` (it's `!Send`).
+    ///
+    /// # Example
+    /// ```
+    /// # use spa_rs::session::SessionStore;
+    /// let store = SessionStore::new("my_session");
+    /// store.insert("session_id", 1);
+    /// for (key, value) in &store.iter_sessions() {
+    ///     println!("{}: {}", key, value);
+    /// }
+    /// ```
+    pub fn iter_sessions(&self) -> SessionIter<'_, T> {
+        SessionIter {
+            guard: self.inner.lock(),
+        }
+    }
+}
+
+/// Locked sessions of [SessionStore], see [SessionStore::iter_sessions]
+pub struct SessionIter<'a, T> {
+    guard: MutexGuard<'a, LruCache<String, T>>,
+}
+
+impl<T> SessionIter<'_, T> {
+    /// iterate `(key, value)` pairs, from most recently used to least recently used
+    pub fn iter(&self) -> Sessions<'_, T> {
+        Sessions(self.guard.iter())
+    }
+
+    /// count of sessions
+    pub fn len(&self) -> usize {
+        self.guard.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guard.is_empty()
+    }
+}
+
+impl<'b, T> IntoIterator for &'b SessionIter<'_, T> {
+    type Item = (&'b str, &'b T);
+    type IntoIter = Sessions<'b, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator of `(key, value)` pairs in [SessionIter]
+pub struct Sessions<'b, T>(lru::Iter<'b, String, T>);
+
+impl<'b, T> Iterator for Sessions<'b, T> {
+    type Item = (&'b str, &'b T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k.as_str(), v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Sessions<'_, T> {}
+
+impl<T: Clone> SessionStore<T> {
+    /// get a copy of the session item by session key, and mark it as recently used
+    pub fn get(&self, k: &str) -> Option<T> {
+        self.inner.lock().get(k).cloned()
+    }
+}
+
+/// Session storages partitioned by tenant, sessions of different tenants are isolated
+///
+/// All tenants share the cookie named `key_prefix`, whose value is `{tenant_id}:{session_id}`,
+/// see [NamespacedSessionStore::cookie_value]. Use [AddSession::namespaced] and
+/// [RequireSession::namespaced] to protect routes with it.
+#[derive(Debug)]
+pub struct NamespacedSessionStore<T> {
+    key: String,
+    namespaces: Mutex<HashMap<String, Arc<SessionStore<T>>>>,
+}
+
+impl<T> NamespacedSessionStore<T> {
+    pub fn new(key_prefix: &str) -> Self {
+        Self {
+            key: key_prefix.to_string(),
+            namespaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// get the cookie name
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// the session store of `tenant_id`, if it was created
+    pub fn namespace(&self, tenant_id: &str) -> Option<Arc<SessionStore<T>>> {
+        self.namespaces.lock().get(tenant_id).cloned()
+    }
+
+    /// the cookie value for `session_id` of `tenant_id`
+    pub fn cookie_value(tenant_id: &str, session_id: &str) -> String {
+        format!("{}:{}", tenant_id, session_id)
+    }
+}
+
+impl<T: PartialEq> NamespacedSessionStore<T> {
+    /// the session store of `tenant_id`, create an empty one if not exists
+    pub fn get_or_create_namespace(&self, tenant_id: &str) -> Arc<SessionStore<T>> {
+        self.namespaces
+            .lock()
+            .entry(tenant_id.to_string())
+            .or_insert_with(|| Arc::new(SessionStore::new(self.key.clone())))
+            .clone()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Stores<T> {
+    Single(Arc<SessionStore<T>>),
+    Namespaced(Arc<NamespacedSessionStore<T>>),
+}
+
+impl<T: Clone> Stores<T> {
+    fn key(&self) -> &str {
+        match self {
+            Stores::Single(store) => &store.key,
+            Stores::Namespaced(store) => &store.key,
+        }
+    }
+
+    fn lookup(&self, v: &str) -> Option<Session<T>> {
+        let store = match self {
+            Stores::Single(store) => store.clone(),
+            Stores::Namespaced(store) => {
+                let (tenant_id, session_id) = v.split_once(':')?;
+                let store = store.namespace(tenant_id)?;
+                return Some(Session {
+                    current: store.get(session_id)?,
+                    all: store,
+                });
+            }
+        };
+        Some(Session {
+            current: store.get(v)?,
+            all: store,
+        })
     }
 }
 
 /// Middleware that can access and modify all sessions data. Usually used for **Login** handler
 ///
+/// If [secret](Self::secret) is set, the [CookieSecret] can access by Extension too, the
+/// cookie value should be signed by [CookieSecret::sign], or it will be rejected by
+/// [RequireSession].
+///
 /// # Example
 ///```
 /// # use spa_rs::routing::{post, Router};
+/// # use spa_rs::http::header;
 /// # use spa_rs::Extension;
-/// # use spa_rs::session::AddSession;
-/// # use spa_rs::session::SessionStore;
+/// # use spa_rs::session::{AddSession, CookieSecret, SessionStore};
 /// # use axum_help::filter::FilterExLayer;
 /// # use std::sync::Arc;
 /// #
 /// #[derive(PartialEq, Clone)]
 /// struct User;
 ///
-/// async fn login(Extension(session): Extension<Arc<SessionStore<User>>>) {
+/// async fn login(
+///     Extension(session): Extension<Arc<SessionStore<User>>>,
+///     Extension(secret): Extension<CookieSecret>,
+/// ) -> [(header::HeaderName, String); 1] {
 ///     let new_user = User;
 ///     session.insert("session_id", new_user);
+///     [(header::SET_COOKIE, format!("my_session={}", secret.sign("session_id")))]
 /// }
 ///
 /// #[tokio::main]
 /// async fn main() {
 ///     let session = Arc::new(SessionStore::<User>::new("my_session"));
+///     let secret = CookieSecret::new(b"secret");
 ///     let app = Router::new()
 ///         .route("/login", post(login))
-///         .layer(FilterExLayer::new(AddSession::new(session.clone())));
-/// #   axum::Server::bind(&"0.0.0.0:3000".parse().unwrap()).serve(app.into_make_service());
+///         .layer(FilterExLayer::new(AddSession::new(session.clone()).secret(secret)));
+/// #   let _: Router = app;
 /// }
 ///```
 #[derive(Clone, Debug)]
-pub struct AddSession<T>(Arc<SessionStore<T>>);
+pub struct AddSession<T> {
+    stores: Stores<T>,
+    secret: Option<CookieSecret>,
+}
 
 impl<T> AddSession<T> {
     pub fn new(store: Arc<SessionStore<T>>) -> Self {
-        Self(store)
+        Self {
+            stores: Stores::Single(store),
+            secret: None,
+        }
+    }
+
+    /// like [new](Self::new), but `Arc<NamespacedSessionStore<T>>` can access by Extension
+    pub fn namespaced(store: Arc<NamespacedSessionStore<T>>) -> Self {
+        Self {
+            stores: Stores::Namespaced(store),
+            secret: None,
+        }
+    }
+
+    /// make `secret` can access by Extension, to sign the cookie values set by handlers
+    pub fn secret(mut self, secret: CookieSecret) -> Self {
+        self.secret = Some(secret);
+        self
     }
 }
 
@@ -96,13 +398,27 @@ where
     type Response = Response;
 
     fn check(&mut self, mut request: Request) -> Result<Self::Request, Self::Response> {
-        request.extensions_mut().insert(self.0.clone());
+        match &self.stores {
+            Stores::Single(store) => {
+                request.extensions_mut().insert(store.clone());
+            }
+            Stores::Namespaced(store) => {
+                request.extensions_mut().insert(store.clone());
+            }
+        }
+        if let Some(secret) = &self.secret {
+            request.extensions_mut().insert(secret.clone());
+        }
         Ok(request)
     }
 }
 
 /// Middleware that can access and modify all sessions data.
 ///
+/// If [secret](Self::secret) is set, only cookie values signed by it are accepted. Without
+/// it the [CookieSecret] set by [SpaServer::cookie_secret](crate::SpaServer::cookie_secret)
+/// is used if present, set it explicitly so the check does not depend on the layer order.
+///
 /// # Example
 ///```
 /// # use spa_rs::routing::{post, Router};
@@ -126,15 +442,36 @@ where
 ///     let app = Router::new()
 ///         .route("/action", post(action))
 ///         .layer(FilterExLayer::new(RequireSession::new(session.clone())));
-/// #   axum::Server::bind(&"0.0.0.0:3000".parse().unwrap()).serve(app.into_make_service());
+/// #   let _: Router = app;
 /// }
 ///```
 #[derive(Clone, Debug)]
-pub struct RequireSession<T>(Arc<SessionStore<T>>);
+pub struct RequireSession<T> {
+    stores: Stores<T>,
+    secret: Option<CookieSecret>,
+}
 
 impl<T> RequireSession<T> {
     pub fn new(store: Arc<SessionStore<T>>) -> Self {
-        Self(store)
+        Self {
+            stores: Stores::Single(store),
+            secret: None,
+        }
+    }
+
+    /// like [new](Self::new), but look up the session in the tenant's store, `Session::all`
+    /// is the store of that tenant
+    pub fn namespaced(store: Arc<NamespacedSessionStore<T>>) -> Self {
+        Self {
+            stores: Stores::Namespaced(store),
+            secret: None,
+        }
+    }
+
+    /// only accept cookie values signed by `secret`
+    pub fn secret(mut self, secret: CookieSecret) -> Self {
+        self.secret = Some(secret);
+        self
     }
 }
 
@@ -147,14 +484,21 @@ where
 
     fn check(&mut self, mut request: Request) -> Result<Self::Request, Self::Response> {
         if let Some(cookie) = request.headers().typed_get::<Cookie>() {
-            let sessions = self.0.inner.read();
+            let secret = self
+                .secret
+                .as_ref()
+                .or_else(|| request.extensions().get::<CookieSecret>());
             for (k, v) in cookie.iter() {
-                if k == self.0.key {
-                    if let Some(u) = sessions.get(v) {
-                        request.extensions_mut().insert(Session {
-                            current: u.clone(),
-                            all: self.0.clone(),
-                        });
+                if k == self.stores.key() {
+                    let v = match secret {
+                        Some(secret) => match secret.verify(v) {
+                            Some(v) => v,
+                            None => continue,
+                        },
+                        None => v,
+                    };
+                    if let Some(session) = self.stores.lookup(v) {
+                        request.extensions_mut().insert(session);
                         return Ok(request);
                     }
                 }
@@ -168,3 +512,174 @@ where
         })
     }
 }
+
+/// Append `attribute` such as `Secure` to every `Set-Cookie` header which doesn't have it yet
+pub(crate) fn add_cookie_attribute(headers: &mut HeaderMap, attribute: &str) {
+    let cookies: Vec<HeaderValue> = headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .cloned()
+        .collect();
+    if cookies.is_empty() {
+        return;
+    }
+
+    headers.remove(header::SET_COOKIE);
+    for cookie in cookies {
+        let present = cookie.to_str().map_or(true, |c| {
+            c.split(';').skip(1).any(|a| {
+                a.split('=')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .eq_ignore_ascii_case(attribute)
+            })
+        });
+        let cookie = if present {
+            cookie
+        } else {
+            let mut value = cookie.as_bytes().to_vec();
+            value.extend_from_slice(b"; ");
+            value.extend_from_slice(attribute.as_bytes());
+            HeaderValue::from_bytes(&value).unwrap_or(cookie)
+        };
+        headers.append(header::SET_COOKIE, cookie);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        add_cookie_attribute, CookieSecret, NamespacedSessionStore, RequireSession, SessionStore,
+        Stores,
+    };
+    use crate::filter::Predicate;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{header, HeaderMap},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cookie_secret() {
+        let secret = CookieSecret::with_rotation(vec![[1; 32], [2; 32]]);
+        let signed = secret.sign("session_id");
+        assert_eq!(secret.verify(&signed), Some("session_id"));
+        assert_eq!(secret.verify("session_id"), None);
+        assert_eq!(
+            secret.verify(&signed.replace("session_id", "other_id")),
+            None
+        );
+
+        let legacy = CookieSecret::new(&[2; 32]).sign("session_id");
+        assert_eq!(secret.verify(&legacy), Some("session_id"));
+        assert_eq!(CookieSecret::new(&[3; 32]).verify(&legacy), None);
+    }
+
+    #[test]
+    fn test_require_signed_session() {
+        let store = Arc::new(SessionStore::new("my_session"));
+        store.insert("s1", "alice");
+        let secret = CookieSecret::new(b"secret");
+        let request = |cookie: &str| {
+            Request::builder()
+                .header(header::COOKIE, format!("my_session={}", cookie))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let mut require = RequireSession::new(store.clone()).secret(secret.clone());
+        assert!(require.check(request(&secret.sign("s1"))).is_ok());
+        assert!(require.check(request("s1")).is_err());
+        let forged = CookieSecret::new(b"other").sign("s1");
+        assert!(require.check(request(&forged)).is_err());
+
+        // the secret set by SpaServer is used if the layer has none
+        let mut require = RequireSession::new(store);
+        assert!(require.check(request("s1")).is_ok());
+        let mut unsigned = request("s1");
+        unsigned.extensions_mut().insert(secret);
+        assert!(require.check(unsigned).is_err());
+    }
+
+    #[test]
+    fn test_iter_sessions() {
+        let store = SessionStore::new("my_session");
+        store.insert("a", 1);
+        store.insert("b", 2);
+        store.insert("c", 3);
+        assert_eq!(store.get("a"), Some(1));
+
+        let sessions = store.iter_sessions();
+        assert_eq!(sessions.len(), 3);
+        let all: Vec<_> = sessions.iter().collect();
+        assert_eq!(all, [("a", &1), ("c", &3), ("b", &2)]);
+        let mut keys = Vec::new();
+        for (key, _) in &sessions {
+            keys.push(key);
+        }
+        assert_eq!(keys, ["a", "c", "b"]);
+        assert_eq!(sessions.iter().len(), 3);
+        drop(sessions);
+
+        // iterating doesn't update recently used order
+        let keys: Vec<_> = store
+            .iter_sessions()
+            .iter()
+            .map(|(k, _)| k.to_string())
+            .collect();
+        assert_eq!(keys, ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn test_remove_all_by() {
+        let store = SessionStore::new("my_session");
+        store.insert("a1", "alice");
+        store.insert("b1", "bob");
+        store.insert("a2", "alice");
+
+        let mut keys = store.lookup_all_by(|u| *u == "alice");
+        keys.sort();
+        assert_eq!(keys, ["a1", "a2"]);
+        assert_eq!(store.remove_all_by(|u| *u == "alice"), 2);
+        assert!(store.lookup_all_by(|u| *u == "alice").is_empty());
+        assert_eq!(store.get("b1"), Some("bob"));
+    }
+
+    #[test]
+    fn test_namespaced_store() {
+        let store = Arc::new(NamespacedSessionStore::new("my_session"));
+        store.get_or_create_namespace("acme").insert("s1", "alice");
+        store.get_or_create_namespace("globex").insert("s1", "bob");
+
+        let stores = Stores::Namespaced(store.clone());
+        let cookie = NamespacedSessionStore::<&str>::cookie_value("acme", "s1");
+        assert_eq!(stores.lookup(&cookie).map(|s| s.current), Some("alice"));
+        assert_eq!(stores.lookup("globex:s1").map(|s| s.current), Some("bob"));
+        assert!(stores.lookup("initech:s1").is_none());
+        assert!(stores.lookup("s1").is_none());
+        assert!(store.namespace("initech").is_none());
+    }
+
+    #[test]
+    fn test_add_cookie_attribute() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::SET_COOKIE, "a=1; Path=/".parse().unwrap());
+        headers.append(header::SET_COOKIE, "b=secure; secure".parse().unwrap());
+        add_cookie_attribute(&mut headers, "Secure");
+        add_cookie_attribute(&mut headers, "HttpOnly");
+        let cookies: Vec<_> = headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            cookies,
+            [
+                "a=1; Path=/; Secure; HttpOnly",
+                "b=secure; secure; HttpOnly"
+            ]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Hosting [Swagger UI](https://swagger.io/tools/swagger-ui/) alongside the API.
+//!
+//! A minimal OpenAPI viewer is embedded into the binary, so it works in offline and
+//! air-gapped deployments, and no third-party script is loaded into the page.
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+    routing::get,
+    Json, Router,
+};
+
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+    <title>Swagger UI</title>
+    <link rel="stylesheet" href="{{asset_path}}/swagger-ui.css" />
+</head>
+<body>
+    <div id="swagger-ui" data-spec="{{spec_path}}"></div>
+    <script src="{{asset_path}}/swagger-ui.js"></script>
+</body>
+</html>
+"##;
+const SWAGGER_UI_JS: &str = include_str!("swagger/swagger-ui.js");
+const SWAGGER_UI_CSS: &str = include_str!("swagger/swagger-ui.css");
+
+/// escape `s` for html attribute value
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Swagger UI settings collected by [SpaServer](crate::SpaServer)
+#[derive(Default)]
+pub(crate) struct SwaggerUi {
+    pub(crate) path: String,
+    pub(crate) spec_path: String,
+}
+
+impl SwaggerUi {
+    /// Register the ui page, its assets and the spec route into `router`
+    pub(crate) fn register(self, router: Router, spec: serde_json::Value) -> Router {
+        let asset_path = self.path.trim_end_matches('/');
+        let html = SWAGGER_UI_HTML
+            .replace("{{asset_path}}", &escape(asset_path))
+            .replace("{{spec_path}}", &escape(&self.spec_path));
+        router
+            .route(
+                &self.spec_path,
+                get(move || async move { Json(spec).into_response() }),
+            )
+            .route(&self.path, get(move || async move { Html(html) }))
+            .route(
+                &format!("{}/swagger-ui.js", asset_path),
+                get(|| async { ([(header::CONTENT_TYPE, "text/javascript")], SWAGGER_UI_JS) }),
+            )
+            .route(
+                &format!("{}/swagger-ui.css", asset_path),
+                get(|| async { ([(header::CONTENT_TYPE, "text/css")], SWAGGER_UI_CSS) }),
+            )
+    }
+}
@@ -0,0 +1,207 @@
+// A minimal OpenAPI viewer served by spa-rs, so no third-party script is needed
+(function () {
+    "use strict";
+
+    const METHODS = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+    const root = document.getElementById("swagger-ui");
+    let spec = {};
+
+    function el(tag, attrs, ...children) {
+        const node = document.createElement(tag);
+        for (const [key, value] of Object.entries(attrs || {})) {
+            if (key === "class") {
+                node.className = value;
+            } else {
+                node.setAttribute(key, value);
+            }
+        }
+        for (const child of children) {
+            if (child !== null && child !== undefined) {
+                node.append(child instanceof Node ? child : String(child));
+            }
+        }
+        return node;
+    }
+
+    // resolve local `$ref` such as `#/components/schemas/User`, cycles are left as is
+    function resolve(value, seen = new Set()) {
+        if (Array.isArray(value)) {
+            return value.map((v) => resolve(v, seen));
+        }
+        if (!value || typeof value !== "object") {
+            return value;
+        }
+        if (typeof value.$ref === "string" && value.$ref.startsWith("#/")) {
+            if (seen.has(value.$ref)) {
+                return { $ref: value.$ref };
+            }
+            const target = value.$ref
+                .slice(2)
+                .split("/")
+                .reduce((v, k) => (v ? v[k.replace(/~1/g, "/").replace(/~0/g, "~")] : v), spec);
+            return resolve(target, new Set([...seen, value.$ref]));
+        }
+        const resolved = {};
+        for (const [key, v] of Object.entries(value)) {
+            resolved[key] = resolve(v, seen);
+        }
+        return resolved;
+    }
+
+    function json(value) {
+        return el("pre", null, JSON.stringify(value, null, 2));
+    }
+
+    function parameterTable(parameters, inputs) {
+        const rows = parameters.map((p) => {
+            const input = el("input", { placeholder: p.name });
+            inputs.push([p, input]);
+            return el(
+                "tr",
+                null,
+                el("td", null, el("b", null, p.name), p.required ? " *" : null, el("br"), el("i", null, p.in)),
+                el("td", null, p.description || "", p.schema ? json(p.schema) : null),
+                el("td", null, input)
+            );
+        });
+        return el(
+            "table",
+            null,
+            el("tr", null, el("th", null, "Name"), el("th", null, "Description"), el("th", null, "Value")),
+            ...rows
+        );
+    }
+
+    async function execute(method, path, inputs, body, output) {
+        let url = path;
+        const query = new URLSearchParams();
+        const headers = {};
+        for (const [p, input] of inputs) {
+            if (input.value === "") {
+                continue;
+            }
+            if (p.in === "path") {
+                url = url.replace(`{${p.name}}`, encodeURIComponent(input.value));
+            } else if (p.in === "query") {
+                query.append(p.name, input.value);
+            } else if (p.in === "header") {
+                headers[p.name] = input.value;
+            }
+        }
+        if ([...query].length > 0) {
+            url += `?${query}`;
+        }
+        const init = { method: method.toUpperCase(), headers };
+        if (body && body.value !== "") {
+            headers["Content-Type"] = "application/json";
+            init.body = body.value;
+        }
+
+        output.replaceChildren("Loading...");
+        try {
+            const response = await fetch(url, init);
+            const text = await response.text();
+            output.replaceChildren(
+                el("p", null, el("b", null, `${response.status} ${response.statusText}`), ` ${init.method} ${url}`),
+                el("pre", null, text)
+            );
+        } catch (e) {
+            output.replaceChildren(el("p", { class: "error" }, String(e)));
+        }
+    }
+
+    function operation(path, method, op, shared) {
+        const parameters = resolve([...(shared || []), ...(op.parameters || [])]);
+        const requestBody = resolve(op.requestBody);
+        const inputs = [];
+        const details = el("div", { class: "body" });
+        if (op.description) {
+            details.append(el("p", null, op.description));
+        }
+        if (parameters.length > 0) {
+            details.append(el("h4", null, "Parameters"), parameterTable(parameters, inputs));
+        }
+
+        let body = null;
+        if (requestBody) {
+            body = el("textarea", { placeholder: "request body" });
+            const content = requestBody.content || {};
+            const schema = (content["application/json"] || Object.values(content)[0] || {}).schema;
+            details.append(
+                el("h4", null, "Request body", requestBody.required ? " *" : null),
+                el("p", null, requestBody.description || Object.keys(content).join(", "))
+            );
+            if (schema) {
+                details.append(json(schema));
+            }
+            details.append(body);
+        }
+
+        const responses = resolve(op.responses || {});
+        details.append(
+            el("h4", null, "Responses"),
+            el(
+                "table",
+                null,
+                el("tr", null, el("th", null, "Code"), el("th", null, "Description")),
+                ...Object.entries(responses).map(([code, r]) => {
+                    const content = (r && r.content) || {};
+                    const schema = (content["application/json"] || Object.values(content)[0] || {}).schema;
+                    return el("tr", null, el("td", null, code), el("td", null, (r && r.description) || "", schema ? json(schema) : null));
+                })
+            )
+        );
+
+        const output = el("div");
+        const button = el("button", null, "Execute");
+        button.addEventListener("click", () => execute(method, path, inputs, body, output));
+        details.append(button, output);
+
+        const classes = `operation ${method}${op.deprecated ? " deprecated" : ""}`;
+        return el(
+            "details",
+            { class: classes },
+            el("summary", null, el("span", { class: "method" }, method), el("span", { class: "path" }, path), el("span", { class: "summary" }, op.summary || "")),
+            details
+        );
+    }
+
+    function render() {
+        const info = spec.info || {};
+        root.replaceChildren(el("h1", null, info.title || "API", info.version ? el("small", null, info.version) : null));
+        if (info.description) {
+            root.append(el("p", null, info.description));
+        }
+
+        const tags = new Map();
+        for (const [path, item] of Object.entries(spec.paths || {})) {
+            for (const method of METHODS) {
+                const op = item[method];
+                if (!op) {
+                    continue;
+                }
+                const tag = (op.tags && op.tags[0]) || "default";
+                if (!tags.has(tag)) {
+                    tags.set(tag, []);
+                }
+                tags.get(tag).push(operation(path, method, op, item.parameters));
+            }
+        }
+        for (const [tag, operations] of tags) {
+            root.append(el("h2", null, tag), ...operations);
+        }
+    }
+
+    fetch(root.dataset.spec)
+        .then((response) => {
+            if (!response.ok) {
+                throw new Error(`load spec error: ${response.status} ${response.statusText}`);
+            }
+            return response.json();
+        })
+        .then((loaded) => {
+            spec = loaded;
+            render();
+        })
+        .catch((e) => root.replaceChildren(el("p", { class: "error" }, String(e))));
+})();
@@ -0,0 +1,133 @@
+//! Rustls config, client certificate support, see [SpaServer::client_ca](crate::SpaServer::client_ca)
+use crate::{auth::PeerCertificates, HttpsConfig};
+use anyhow::{anyhow, Result};
+use axum::{middleware::AddExtension, Extension, Router};
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+use futures_util::future::BoxFuture;
+use rustls::{
+    server::{
+        AllowAnyAnonymousOrAuthenticatedClient, ClientHello, ResolvesServerCert,
+        ResolvesServerCertUsingSni,
+    },
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use std::{io, sync::Arc};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+
+/// ALPN protocols advertised by default, http/2 is preferred
+pub(crate) const DEFAULT_ALPN_PROTOCOLS: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// A domain hosted by [SpaServer::sni_routing](crate::SpaServer::sni_routing)
+pub struct SniRoute {
+    /// domain name matched with SNI in tls handshake and `Host` header of requests
+    pub domain: String,
+    pub cert_pem: Vec<u8>,
+    pub key_pem: Vec<u8>,
+    pub router: Router,
+}
+
+fn load_pems(config: &HttpsConfig) -> Result<(Vec<Certificate>, PrivateKey)> {
+    let certs = rustls_pemfile::certs(&mut config.certificate.as_slice())
+        .map(|cert| cert.map(|cert| Certificate(cert.to_vec())))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut config.private_key.as_slice())?
+        .ok_or_else(|| anyhow!("no private key found in pem"))?;
+    Ok((certs, PrivateKey(key.secret_der().to_vec())))
+}
+
+/// Resolves certificate by SNI, the default one is used for clients without SNI or with
+/// unknown domains
+struct SniResolver {
+    domains: ResolvesServerCertUsingSni,
+    default: Arc<CertifiedKey>,
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.domains
+            .resolve(client_hello)
+            .or_else(|| Some(self.default.clone()))
+    }
+}
+
+fn certified_key(config: &HttpsConfig) -> Result<CertifiedKey> {
+    let (certs, key) = load_pems(config)?;
+    Ok(CertifiedKey::new(certs, sign::any_supported_type(&key)?))
+}
+
+/// Build rustls config from pems, client certificates signed by `client_ca` are requested
+/// if it is set, certificates of `sni_certs` are chosen by the domain in SNI
+pub(crate) async fn rustls_config(
+    config: HttpsConfig,
+    client_ca: Option<Vec<u8>>,
+    alpn_protocols: Vec<Vec<u8>>,
+    sni_certs: Vec<(String, HttpsConfig)>,
+) -> Result<RustlsConfig> {
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match client_ca {
+        Some(client_ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut client_ca.as_slice()) {
+                roots.add(&Certificate(cert?.to_vec()))?;
+            }
+            // anonymous clients are allowed in handshake, so the rejection can be a http response
+            builder.with_client_cert_verifier(
+                AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed(),
+            )
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = if sni_certs.is_empty() {
+        let (certs, key) = load_pems(&config)?;
+        builder.with_single_cert(certs, key)?
+    } else {
+        let mut domains = ResolvesServerCertUsingSni::new();
+        for (domain, config) in &sni_certs {
+            domains
+                .add(domain, certified_key(config)?)
+                .map_err(|e| anyhow!("add certificate of {} error: {}", domain, e))?;
+        }
+        builder.with_cert_resolver(Arc::new(SniResolver {
+            domains,
+            default: Arc::new(certified_key(&config)?),
+        }))
+    };
+    server_config.alpn_protocols = alpn_protocols;
+
+    Ok(RustlsConfig::from_config(Arc::new(server_config)))
+}
+
+/// Acceptor which inserts [PeerCertificates] of the connection into every request
+#[derive(Clone)]
+pub(crate) struct PeerCertAcceptor(pub(crate) RustlsAcceptor);
+
+impl<I, S> Accept<I, S> for PeerCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, PeerCertificates>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let accept = self.0.accept(stream, service);
+        Box::pin(async move {
+            let (stream, service) = accept.await?;
+            let certificates = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .map(|c| c.to_vec())
+                .unwrap_or_default();
+            let service = Extension(PeerCertificates(certificates.into())).layer(service);
+            Ok((stream, service))
+        })
+    }
+}
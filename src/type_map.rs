@@ -0,0 +1,94 @@
+//! A map of typed values, used to insert many extensions at once.
+use axum::http::Extensions;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+type Inserter = Arc<dyn Fn(&mut Extensions) + Send + Sync>;
+
+/// A map holding at most one value of each type, see [SpaServer::global_extensions](crate::SpaServer::global_extensions)
+///
+/// # Example
+/// ```
+/// # use spa_rs::TypeMap;
+/// #[derive(Clone)]
+/// struct AppConfig;
+///
+/// let mut map = TypeMap::new();
+/// map.insert(AppConfig);
+/// map.insert(42u32);
+/// assert_eq!(map.len(), 2);
+/// ```
+#[derive(Clone, Default)]
+pub struct TypeMap {
+    values: HashMap<TypeId, Inserter>,
+}
+
+impl TypeMap {
+    /// return an empty TypeMap
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// insert a value, the previous value of the same type is replaced
+    pub fn insert<T: Any + Clone + Send + Sync>(&mut self, value: T) -> &mut Self {
+        self.values.insert(
+            TypeId::of::<T>(),
+            Arc::new(move |extensions| {
+                extensions.insert(value.clone());
+            }),
+        );
+        self
+    }
+
+    /// whether a value of type `T` is inserted
+    pub fn contains<T: Any>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    /// count of values
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// insert a copy of all values into `extensions`
+    pub(crate) fn apply(&self, extensions: &mut Extensions) {
+        for insert in self.values.values() {
+            insert(extensions);
+        }
+    }
+}
+
+impl std::fmt::Debug for TypeMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypeMap")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TypeMap;
+    use axum::http::Extensions;
+
+    #[test]
+    fn test_type_map() {
+        let mut map = TypeMap::new();
+        map.insert(1u32).insert("config").insert(2u32);
+        assert_eq!(map.len(), 2);
+        assert!(map.contains::<u32>());
+        assert!(!map.contains::<u64>());
+
+        let mut extensions = Extensions::new();
+        map.apply(&mut extensions);
+        assert_eq!(extensions.get::<u32>(), Some(&2));
+        assert_eq!(extensions.get::<&str>(), Some(&"config"));
+    }
+}
@@ -0,0 +1,267 @@
+//! Forwarding websocket frames to upstream, see [SpaServer::proxy_websocket](crate::SpaServer::proxy_websocket)
+use axum::{
+    extract::ws::{self, WebSocket},
+    http::{header, HeaderMap, HeaderName},
+    Router,
+};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, warn};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::{collections::HashMap, sync::Arc};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, client::IntoClientRequest, protocol::frame::coding::CloseCode},
+};
+
+/// Which client request headers are sent to upstream, see [SpaServer::forward_headers](crate::SpaServer::forward_headers)
+#[derive(Clone, Debug, Default)]
+pub(crate) enum ForwardHeaders {
+    #[default]
+    None,
+    Only(Arc<[HeaderName]>),
+    AllExcept(Arc<[HeaderName]>),
+}
+
+/// Routes added by [SpaServer::proxy_websocket](crate::SpaServer::proxy_websocket), they are
+/// wrapped by [ProxyHeadersLayer](crate::middleware::ProxyHeadersLayer) when building
+#[derive(Default)]
+pub(crate) struct WsProxies {
+    pub(crate) router: Router,
+    pub(crate) forward_headers: ForwardHeaders,
+}
+
+impl ForwardHeaders {
+    /// headers of handshake and connection are generated for upstream, never forwarded
+    const NEVER: [HeaderName; 9] = [
+        header::HOST,
+        header::CONNECTION,
+        header::UPGRADE,
+        header::CONTENT_LENGTH,
+        header::TRANSFER_ENCODING,
+        header::SEC_WEBSOCKET_KEY,
+        header::SEC_WEBSOCKET_VERSION,
+        header::SEC_WEBSOCKET_EXTENSIONS,
+        header::SEC_WEBSOCKET_ACCEPT,
+    ];
+
+    /// also forward `names`, such as headers set by proxy
+    pub(crate) fn include<'a>(self, names: impl IntoIterator<Item = &'a HeaderName>) -> Self {
+        let names: Vec<_> = names.into_iter().cloned().collect();
+        if names.is_empty() {
+            return self;
+        }
+
+        match self {
+            ForwardHeaders::None => ForwardHeaders::Only(names.into()),
+            ForwardHeaders::Only(only) => {
+                ForwardHeaders::Only(only.iter().cloned().chain(names).collect())
+            }
+            ForwardHeaders::AllExcept(strip) => ForwardHeaders::AllExcept(
+                strip
+                    .iter()
+                    .filter(|n| !names.contains(n))
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
+
+    pub(crate) fn select(&self, headers: &HeaderMap) -> HeaderMap {
+        let forwarded = |name: &HeaderName| {
+            !Self::NEVER.contains(name)
+                && match self {
+                    ForwardHeaders::None => false,
+                    ForwardHeaders::Only(names) => names.contains(name),
+                    ForwardHeaders::AllExcept(names) => !names.contains(name),
+                }
+        };
+
+        headers
+            .iter()
+            .filter(|(name, _)| forwarded(name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// characters escaped in a substituted path parameter, so it stays in one path segment
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// replace `{name}` in `to` with percent-encoded path parameters, and append query string if
+/// `to` has none
+///
+/// Returns `None` if a parameter is `.` or `..`, which would escape the path of `to`.
+pub(crate) fn upstream_url(
+    to: &str,
+    params: &HashMap<String, String>,
+    query: Option<&str>,
+) -> Option<String> {
+    let mut url = to.to_string();
+    for (k, v) in params {
+        if v == "." || v == ".." {
+            return None;
+        }
+        let v = utf8_percent_encode(v, SEGMENT).to_string();
+        url = url.replace(&format!("{{{}}}", k), &v);
+    }
+    if let Some(query) = query {
+        if !url.contains('?') {
+            url.push('?');
+            url.push_str(query);
+        }
+    }
+
+    Some(url)
+}
+
+fn to_upstream(msg: ws::Message) -> Option<tungstenite::Message> {
+    Some(match msg {
+        ws::Message::Text(text) => tungstenite::Message::Text(text),
+        ws::Message::Binary(data) => tungstenite::Message::Binary(data),
+        ws::Message::Close(frame) => {
+            tungstenite::Message::Close(frame.map(|f| tungstenite::protocol::CloseFrame {
+                code: CloseCode::from(f.code),
+                reason: f.reason,
+            }))
+        }
+        // keepalive is handled by each side itself
+        ws::Message::Ping(_) | ws::Message::Pong(_) => return None,
+    })
+}
+
+fn to_client(msg: tungstenite::Message) -> Option<ws::Message> {
+    Some(match msg {
+        tungstenite::Message::Text(text) => ws::Message::Text(text),
+        tungstenite::Message::Binary(data) => ws::Message::Binary(data),
+        tungstenite::Message::Close(frame) => ws::Message::Close(frame.map(|f| ws::CloseFrame {
+            code: f.code.into(),
+            reason: f.reason,
+        })),
+        tungstenite::Message::Ping(_)
+        | tungstenite::Message::Pong(_)
+        | tungstenite::Message::Frame(_) => return None,
+    })
+}
+
+/// Forward frames between `client` and `url` in both directions, until both sides closed
+///
+/// When one side closes or fails, a close frame is sent to the other side.
+pub(crate) async fn proxy(client: WebSocket, url: String, headers: HeaderMap) {
+    let connect = async {
+        let mut request = url.as_str().into_client_request()?;
+        request.headers_mut().extend(headers);
+        connect_async(request).await
+    };
+    let upstream = match connect.await {
+        Ok((upstream, _)) => upstream,
+        Err(e) => {
+            warn!("connect to upstream websocket {} error: {}", url, e);
+            let mut client = client;
+            let _ = client
+                .send(ws::Message::Close(Some(ws::CloseFrame {
+                    code: ws::close_code::ERROR,
+                    reason: "upstream unavailable".into(),
+                })))
+                .await;
+            return;
+        }
+    };
+
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+    let to_upstream_task = tokio::spawn(async move {
+        while let Some(Ok(msg)) = client_rx.next().await {
+            let closing = matches!(msg, ws::Message::Close(_));
+            if let Some(msg) = to_upstream(msg) {
+                if upstream_tx.send(msg).await.is_err() || closing {
+                    break;
+                }
+            }
+        }
+        let _ = upstream_tx.close().await;
+    });
+    let to_client_task = tokio::spawn(async move {
+        while let Some(Ok(msg)) = upstream_rx.next().await {
+            let closing = matches!(msg, tungstenite::Message::Close(_));
+            if let Some(msg) = to_client(msg) {
+                if client_tx.send(msg).await.is_err() || closing {
+                    break;
+                }
+            }
+        }
+        let _ = client_tx.close().await;
+    });
+
+    let _ = tokio::join!(to_upstream_task, to_client_task);
+    debug!("websocket proxy to {} closed", url);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{upstream_url, ForwardHeaders};
+    use axum::http::{header, HeaderMap, HeaderValue};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_upstream_url() {
+        let params = HashMap::from([("id".to_string(), "5".to_string())]);
+        assert_eq!(
+            upstream_url("ws://upstream/{id}", &params, Some("a=1")).unwrap(),
+            "ws://upstream/5?a=1"
+        );
+        assert_eq!(
+            upstream_url("ws://upstream/{id}?b=2", &params, Some("a=1")).unwrap(),
+            "ws://upstream/5?b=2"
+        );
+        assert_eq!(
+            upstream_url("ws://upstream", &HashMap::new(), None).unwrap(),
+            "ws://upstream"
+        );
+
+        let params = HashMap::from([("id".to_string(), "../admin?x=1#y".to_string())]);
+        assert_eq!(
+            upstream_url("ws://upstream/{id}", &params, None).unwrap(),
+            "ws://upstream/..%2Fadmin%3Fx=1%23y"
+        );
+        let params = HashMap::from([("id".to_string(), "..".to_string())]);
+        assert_eq!(upstream_url("ws://upstream/{id}", &params, None), None);
+    }
+
+    #[test]
+    fn test_forward_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en"));
+        headers.insert(header::COOKIE, HeaderValue::from_static("a=1"));
+        headers.insert(header::SEC_WEBSOCKET_KEY, HeaderValue::from_static("key"));
+
+        assert!(ForwardHeaders::None.select(&headers).is_empty());
+        let only = ForwardHeaders::Only([header::ACCEPT_LANGUAGE].into()).select(&headers);
+        assert_eq!(only.len(), 1);
+        assert!(only.contains_key(header::ACCEPT_LANGUAGE));
+        let except = ForwardHeaders::AllExcept([header::COOKIE].into()).select(&headers);
+        assert_eq!(except.len(), 1);
+        assert!(except.contains_key(header::ACCEPT_LANGUAGE));
+
+        let included = ForwardHeaders::None
+            .include([&header::COOKIE])
+            .select(&headers);
+        assert_eq!(included.len(), 1);
+        assert!(included.contains_key(header::COOKIE));
+        let included = ForwardHeaders::AllExcept([header::COOKIE].into())
+            .include([&header::COOKIE])
+            .select(&headers);
+        assert_eq!(included.len(), 2);
+    }
+}